@@ -0,0 +1,43 @@
+//! Compares generating every combination in a hand from its bitboard
+//! against checking every subset of the hand, which is what hints used to
+//! do. Run with `cargo bench`.
+
+extern crate baraha;
+
+use std::time::{Duration, Instant};
+
+use baraha::game::{hand, Game, RuleSet};
+
+fn time<F: FnMut(&Game) -> usize>(name: &str, games: &[Game], mut f: F)
+                                  -> Duration {
+    let start = Instant::now();
+    let mut found = 0;
+    for game in games {
+        found += f(game);
+    }
+    let elapsed = start.elapsed();
+    println!("{:>12}: {:>10.3} ms for {} positions ({} moves)",
+             name,
+             elapsed.as_secs_f64() * 1000.0,
+             games.len(),
+             found);
+    elapsed
+}
+
+fn main() {
+    let games: Vec<_> = (0..200)
+        .map(|seed| Game::with_seed(RuleSet::default(), seed))
+        .collect();
+    let scan = time("subset scan", &games, |g| {
+        hand::subset_scan(&g.hand(g.turn().player()), g.rules()).len()
+    });
+    let gen = time("generator", &games, |g| {
+        let hand = g.hand_bits(g.turn().player());
+        hand::combinations(hand, g.rules(), None).len()
+    });
+    println!("the generator is {:.0}x faster",
+             scan.as_secs_f64() / gen.as_secs_f64());
+    time("legal moves", &games, |g| {
+        g.legal_moves(g.turn().player()).len()
+    });
+}
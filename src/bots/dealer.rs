@@ -1,12 +1,17 @@
-use std::rc::Rc;
+use std::fmt;
+use std::error;
+use std::sync::Arc;
+use std::path::PathBuf;
 
-use game::{self, PlayerNum, Game};
+use game::{self, PlayerNum, Cards, Event, Game, GameError, LoadError,
+           ParseError, RuleSet, Match, MatchEnd};
+use game::save::{parse_cards, parse_player};
 
 enum State {
     Start,
     Wait(PlayerNum),
     Deal,
-    Play(Rc<Game>),
+    Play(Arc<Game>),
     Error,
     End,
 }
@@ -20,31 +25,194 @@ impl State {
         }
     }
     fn has_ended(&self) -> bool {
-        match *self {
-            State::End | State::Error => true,
-            _ => false,
-        }
+        matches!(*self, State::End | State::Error)
     }
 }
 
 pub enum Output {
     You(PlayerNum),
+    Rules(RuleSet),
     Deal(PlayerNum, Vec<game::Card>),
     Turn(game::Turn),
     Play(PlayerNum, game::Cards),
     Win(PlayerNum),
+    Counts(Vec<usize>),
     End(Vec<PlayerNum>),
+    Score(usize, Vec<usize>),
+    MatchEnd(Vec<PlayerNum>),
     Error(String),
-    PlayError(PlayerNum, String),
+    PlayError(PlayerNum, InputError),
+}
+
+/// Why a player's input was rejected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InputError {
+    Command,
+    Cards(ParseError),
+    Play(GameError),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InputError::Command => write!(f, "invalid input"),
+            InputError::Cards(e) => write!(f, "{}", e),
+            InputError::Play(e) => write!(f, "{}", e),
+        }
+    }
 }
 
+impl error::Error for InputError {}
+
+
+/// Table settings chosen by whoever hosts the game.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Shuffle with this seed instead of a random one. Later rounds of a
+    /// match use the following seeds.
+    pub seed: Option<u64>,
+    pub rules: RuleSet,
+    pub end: MatchEnd,
+    /// Whether the winner of a round leads the next one instead of the
+    /// holder of the lowest card.
+    pub winner_leads: bool,
+    /// Where the server saves the match after every move, and resumes it
+    /// from if the file exists when it starts.
+    pub checkpoint: Option<PathBuf>,
+    /// Where the server writes a record of every round played to the end.
+    pub records: Option<PathBuf>,
+    /// How many seats of every table the server fills with its own bots,
+    /// from the first.
+    pub bots: usize,
+    /// The strategy, as taken by `strategy::by_name`, that plays for the
+    /// server's bots and for players while they are away.
+    pub strategy: String,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            seed: None,
+            rules: RuleSet::default(),
+            end: MatchEnd::Rounds(1),
+            winner_leads: true,
+            checkpoint: None,
+            records: None,
+            bots: 0,
+            strategy: "greedy".into(),
+        }
+    }
+}
 
 pub struct Dealer {
     state: State,
+    options: Options,
+    score: Match,
+    /// How many cards each player held when someone first went out.
+    round_counts: Option<Vec<usize>>,
+    last_winner: Option<PlayerNum>,
+    /// A round to continue once everyone has joined.
+    resume: Option<Game>,
+    /// The last round that was played to the end.
+    finished: Option<Arc<Game>>,
 }
 
-pub fn new() -> Dealer {
-    Dealer{state: State::Start}
+pub fn with_options(options: Options) -> Dealer {
+    let score = Match::new(options.rules.players, options.end);
+    Dealer {
+        state: State::Start,
+        options,
+        score,
+        round_counts: None,
+        last_winner: None,
+        resume: None,
+        finished: None,
+    }
+}
+
+/// Reads a `P` line into the cards played, valued under `rules`.
+fn parse_play(inp: &str, rules: &RuleSet) -> Result<Cards, InputError> {
+    let tokens: Vec<_> = inp.split_whitespace().collect();
+    match tokens.split_first() {
+        Some((&"P", cards)) => {
+            parse_cards(cards, rules).map_err(InputError::Cards)
+        }
+        _ => Err(InputError::Command),
+    }
+}
+
+/// Resumes a match saved with `Dealer::save`. The players have to join
+/// again, and take their seats in the order they do.
+pub fn restore(s: &str) -> Result<Dealer, LoadError> {
+    let mut options = Options::default();
+    let mut round = 0;
+    let mut scores = vec![];
+    let mut round_counts = None;
+    let mut last_winner = None;
+    let mut resume = None;
+    let mut lines = s.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let invalid = LoadError::Line(i+1);
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        let nums = || -> Result<Vec<usize>, LoadError> {
+            tokens[1..].iter()
+                .map(|t| t.parse().map_err(|_| invalid.clone()))
+                .collect()
+        };
+        match (tokens.first().cloned(), tokens.get(1).cloned()) {
+            (None, _) => (),
+            (Some("seed"), Some(seed)) => {
+                options.seed = Some(seed.parse().map_err(|_| invalid)?);
+            }
+            (Some("end"), Some(end)) => {
+                let n = match tokens.get(2).map(|t| t.parse()) {
+                    Some(Ok(n)) => n,
+                    _ => return Err(invalid),
+                };
+                options.end = match end {
+                    "rounds" => MatchEnd::Rounds(n),
+                    "target" => MatchEnd::Target(n),
+                    _ => return Err(invalid),
+                };
+            }
+            (Some("winner-leads"), Some(flag)) => {
+                options.winner_leads = match flag {
+                    "yes" => true,
+                    "no" => false,
+                    _ => return Err(invalid),
+                };
+            }
+            (Some("rules"), _) => {
+                options.rules = tokens[1..].join(" ").parse()?;
+            }
+            (Some("scores"), Some(_)) => {
+                scores = nums()?;
+                round = scores.remove(0);
+            }
+            (Some("counts"), Some(_)) => round_counts = Some(nums()?),
+            (Some("winner"), Some(p)) => {
+                last_winner = Some(parse_player(p).ok_or(invalid)?);
+            }
+            (Some("game"), None) => {
+                let rest: Vec<_> = lines.by_ref().map(|(_, l)| l).collect();
+                resume = Some(Game::load(&rest.join("\n"))?);
+            }
+            _ => return Err(invalid),
+        }
+    }
+    if scores.len() != options.rules.players {
+        return Err(LoadError::Mismatch("scores"))
+    }
+    let score = Match::from_scores(options.end, round, scores);
+    Ok(Dealer {
+        state: State::Start,
+        options,
+        score,
+        round_counts,
+        last_winner,
+        resume,
+        finished: None,
+    })
 }
 
 impl Dealer {
@@ -55,7 +223,84 @@ impl Dealer {
         (outputs, self.state.player_input(), self.state.has_ended())
     }
 
+    /// Plays `cards` for whoever's turn it is, as `actuate` does with
+    /// their `P` line. Does nothing unless a round is being played.
+    pub fn play(&mut self, cards: Cards)
+                -> (Vec<Output>, Option<PlayerNum>, bool) {
+        let outputs = self.play_cards(cards);
+        (outputs, self.state.player_input(), self.state.has_ended())
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    /// The round being played, if any.
+    pub fn game(&self) -> Option<&Game> {
+        match self.state {
+            State::Play(ref game) => Some(game),
+            _ => None,
+        }
+    }
+
+    /// What a player who lost their connection needs to be told again to
+    /// pick up the round being played. Empty between rounds.
+    pub fn catch_up(&self) -> Vec<Output> {
+        self.game().map_or(vec![], |game| self.round_state(game))
+    }
+
+    /// The last round that was played to the end, if any.
+    pub fn finished(&self) -> Option<&Game> {
+        self.finished.as_deref()
+    }
+
+    /// The match so far as text that `restore` reads back, or `None` unless
+    /// a round is being dealt or played.
+    pub fn save(&self) -> Option<String> {
+        let game = match self.state {
+            State::Deal => None,
+            State::Play(ref game) => Some(game),
+            _ => return None,
+        };
+        let mut lines = vec![];
+        if let Some(seed) = self.options.seed {
+            lines.push(format!("seed {}", seed));
+        }
+        lines.push(match self.options.end {
+            MatchEnd::Rounds(n) => format!("end rounds {}", n),
+            MatchEnd::Target(n) => format!("end target {}", n),
+        });
+        lines.push(format!("winner-leads {}",
+                           if self.options.winner_leads { "yes" } else { "no" }));
+        lines.push(format!("rules {}", self.options.rules));
+        let mut scores = vec![self.score.round()];
+        scores.extend_from_slice(self.score.scores());
+        let scores: Vec<_> = scores.iter().map(|s| s.to_string()).collect();
+        lines.push(format!("scores {}", scores.join(" ")));
+        if let Some(ref counts) = self.round_counts {
+            let counts: Vec<_> = counts.iter().map(|n| n.to_string())
+                .collect();
+            lines.push(format!("counts {}", counts.join(" ")));
+        }
+        if let Some(p) = self.last_winner {
+            lines.push(format!("winner #{}", p));
+        }
+        let mut text = lines.join("\n") + "\n";
+        if let Some(game) = game {
+            text.push_str("game\n");
+            text.push_str(&game.save());
+        }
+        Some(text)
+    }
+
     fn transition(&mut self, inp: &str) -> Vec<Output> {
+        if let State::Play(ref game) = self.state {
+            let player = game.turn().player();
+            return match parse_play(inp, game.rules()) {
+                Ok(cards) => self.play_cards(cards),
+                Err(e) => vec![Output::PlayError(player, e)],
+            }
+        }
         let (new_state, outputs) = match self.state {
             State::Start => {
                 (State::Wait(1), vec![Output::You(1)])
@@ -64,69 +309,249 @@ impl Dealer {
                 if inp.chars().nth(0).unwrap() != 'G' {
                     (State::Error,
                      vec![Output::Error(format!("#{} is not ready.", x))])
-                } else if x == 4 {
-                    (State::Deal, vec![])
+                } else if x == self.options.rules.players {
+                    match self.resume.take() {
+                        Some(game) => self.resume_round(game),
+                        None => (State::Deal, vec![]),
+                    }
                 } else {
                     (State::Wait(x+1), vec![Output::You(x+1)])
                 }
             }
             State::Deal => {
-                let game = Rc::new(Game::new());
-                let mut outputs = vec![];
-                for p in 1..5 {
+                let rules = self.options.rules.clone();
+                let round = self.score.round() as u64;
+                let mut game = match self.options.seed {
+                    Some(seed) => {
+                        Game::with_seed(rules.clone(), seed.wrapping_add(round))
+                    }
+                    None => Game::new(rules.clone()),
+                };
+                if let Some(winner) = self.last_winner {
+                    if self.options.winner_leads {
+                        game.set_leader(winner);
+                    }
+                }
+                let game = Arc::new(game);
+                self.round_counts = None;
+                let mut outputs = vec![Output::Rules(rules)];
+                for p in 1..game.players()+1 {
                      outputs.push(Output::Deal(p, game.hand(p)));
                 }
+                outputs.push(Output::Counts(game.card_counts()));
                 let turn = game.turn();
-                println!("Game is starting. #{} to start.", turn.player());
+                println!("Round {} is starting with seed {}. #{} to start.",
+                         round + 1, game.seed().unwrap(), turn.player());
                 outputs.push(Output::Turn(turn));
                 (State::Play(game), outputs)
             }
+            _ => unreachable!()
+        };
+        self.state = new_state;
+        outputs
+    }
+
+    fn play_cards(&mut self, cards: Cards) -> Vec<Output> {
+        let (new_state, outputs) = match self.state {
             State::Play(ref mut game) => {
-                let tokens: Vec<_> = inp.trim().splitn(2, ' ').collect();
                 let player = game.turn().player();
-                if tokens.len() == 0 || tokens[0] != "P" {
-                    (State::Play(game.clone()),
-                     vec![Output::PlayError(player, "invalid input".into())])
-                } else {
-                    let token = if tokens.len() == 1 { "" } else { tokens[1] };
-                    match token.parse() {
-                        Ok(cards) => {
-                            match Rc::get_mut(game).unwrap().play(&cards) {
-                                Ok(wins) => {
-                                    let mut outputs = vec![];
-                                    outputs.push(Output::Play(player, cards));
-                                    if wins {
-                                        outputs.push(Output::Win(player));
-                                    }
-                                    let turn = game.turn();
-                                    match turn {
-                                        game::Turn::End => {
-                                            outputs.push(
-                                                Output::End(game.winners()));
-                                            (State::End, outputs)
-                                        }
-                                        _ => {
-                                            outputs.push(Output::Turn(turn));
-                                            (State::Play(game.clone()), outputs)
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    (State::Play(game.clone()),
-                                     vec![Output::PlayError(player, e.into())])
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            (State::Play(game.clone()),
-                             vec![Output::PlayError(player, e.into())])
-                        }
+                let wins = match Arc::get_mut(game).unwrap().play(&cards) {
+                    Ok(wins) => wins,
+                    Err(e) => {
+                        return vec![Output::PlayError(player,
+                                                      InputError::Play(e))]
                     }
+                };
+                let mut outputs = vec![Output::Play(player, cards)];
+                if wins {
+                    outputs.push(Output::Win(player));
+                    if self.round_counts.is_none() {
+                        self.round_counts = Some(game.card_counts());
+                    }
+                }
+                outputs.push(Output::Counts(game.card_counts()));
+                let turn = game.turn();
+                if turn != game::Turn::End {
+                    outputs.push(Output::Turn(turn));
+                    return outputs
+                }
+                let winners = game.winners();
+                self.last_winner = Some(winners[0]);
+                self.finished = Some(game.clone());
+                outputs.push(Output::End(winners));
+                let counts = self.round_counts.take().unwrap();
+                self.score.add_round(&counts);
+                outputs.push(Output::Score(self.score.round(),
+                                           self.score.scores().to_vec()));
+                if self.score.is_over() {
+                    outputs.push(Output::MatchEnd(self.score.ranking()));
+                    (State::End, outputs)
+                } else {
+                    (State::Deal, outputs)
                 }
             }
-            _ => unreachable!()
+            _ => return vec![],
         };
         self.state = new_state;
         outputs
     }
+
+    /// Tells everyone where a restored round is at.
+    fn resume_round(&self, game: Game) -> (State, Vec<Output>) {
+        let outputs = self.round_state(&game);
+        println!("Round {} is resuming. #{} to play.",
+                 self.score.round() + 1, game.turn().player());
+        (State::Play(Arc::new(game)), outputs)
+    }
+
+    /// Everything players need to be told to pick up `game` where it is:
+    /// the deal and every play since, and the passes of the current trick.
+    /// Turns in between are left out, as players act on being told it's
+    /// their turn, so their views only lack the passes of earlier tricks.
+    fn round_state(&self, game: &Game) -> Vec<Output> {
+        let mut outputs = vec![Output::Rules(game.rules().clone())];
+        let history = game.history();
+        let trick = history.iter()
+            .rposition(|e| matches!(*e, Event::NewTrick(_)))
+            .unwrap_or(0);
+        for (i, event) in history.iter().enumerate() {
+            match *event {
+                Event::Deal(ref hands, _) => {
+                    for (q, hand) in hands.iter().enumerate() {
+                        outputs.push(Output::Deal(q+1, hand.cards()));
+                    }
+                    let score = &self.score;
+                    if score.round() > 0 {
+                        outputs.push(Output::Score(score.round(),
+                                                   score.scores().to_vec()));
+                    }
+                    let counts = hands.iter().map(|h| h.len()).collect();
+                    outputs.push(Output::Counts(counts));
+                }
+                Event::Play(q, ref cards) => {
+                    outputs.push(Output::Play(q, cards.clone()))
+                }
+                Event::Pass(q) if i > trick => {
+                    outputs.push(Output::Play(q, Cards::pass()))
+                }
+                Event::Win(q) => outputs.push(Output::Win(q)),
+                _ => (),
+            }
+        }
+        outputs.push(Output::Counts(game.card_counts()));
+        outputs.push(Output::Turn(game.turn()));
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bots::player::{self, ServerInput};
+
+    fn join(dealer: &mut Dealer) {
+        dealer.actuate("");
+        for _ in 0..dealer.options.rules.players {
+            dealer.actuate("G");
+        }
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let options = Options {
+            seed: Some(9),
+            end: MatchEnd::Rounds(2),
+            ..Options::default()
+        };
+        let mut dealer = with_options(options);
+        join(&mut dealer);
+        dealer.actuate("");
+        for _ in 0..7 {
+            let play = match dealer.state {
+                State::Play(ref game) => {
+                    let p = game.turn().player();
+                    format!("P {}", game.legal_moves(p)[0])
+                }
+                _ => unreachable!(),
+            };
+            dealer.actuate(&play);
+        }
+        let saved = dealer.save().unwrap();
+        let mut restored = restore(&saved).unwrap();
+        assert_eq!(None, restored.save());
+        join(&mut restored);
+        assert_eq!(Some(saved), restored.save());
+    }
+
+    #[test]
+    fn play_and_fall_back() {
+        let mut dealer = with_options(Options::default());
+        join(&mut dealer);
+        dealer.actuate("");
+        let game = dealer.game().unwrap().clone();
+        let p = game.turn().player();
+        // Nobody may pass the lead.
+        let (outputs, next, _) = dealer.play(Cards::pass());
+        assert!(matches!(outputs[..], [Output::PlayError(q, _)] if q == p));
+        assert_eq!(Some(p), next);
+        let cards = game.view(p).fallback_move().unwrap();
+        assert_eq!(1, cards.len());
+        let (outputs, _, _) = dealer.play(cards);
+        assert!(matches!(outputs[0], Output::Play(q, _) if q == p));
+    }
+
+    /// What `p` makes of `outputs`, as a player coming back.
+    fn rejoined(outputs: Vec<Output>, p: PlayerNum) -> player::Player {
+        let mut player = player::new(player::Seat::Back("k".into()));
+        player.actuate(None, None);
+        player.actuate(Some(ServerInput::You(p, "k".into())), None);
+        for output in outputs {
+            let input = match output {
+                Output::Rules(rules) => ServerInput::Rules(rules),
+                Output::Deal(q, cards) if q == p => ServerInput::Deal(cards),
+                Output::Deal(..) => continue,
+                Output::Play(q, cards) => ServerInput::Play(q, cards),
+                Output::Win(q) => ServerInput::Win(q),
+                Output::Counts(counts) => ServerInput::Counts(counts),
+                Output::Score(round, scores) => ServerInput::Score(round, scores),
+                Output::Turn(turn) => ServerInput::Turn(turn),
+                _ => unreachable!(),
+            };
+            player.actuate(Some(input), None);
+        }
+        player
+    }
+
+    #[test]
+    fn catch_up() {
+        let options = Options {
+            seed: Some(3),
+            ..Options::default()
+        };
+        let mut dealer = with_options(options);
+        join(&mut dealer);
+        assert!(dealer.catch_up().is_empty());
+        dealer.actuate("");
+        for i in 0..40 {
+            let game = dealer.game().unwrap().clone();
+            for p in 1..game.players()+1 {
+                let player = rejoined(dealer.catch_up(), p);
+                let (view, engine) = (player.view(), game.view(p));
+                assert_eq!(engine.hand, view.hand);
+                assert_eq!(engine.card_counts, view.card_counts);
+                assert_eq!(engine.passed, view.passed);
+                assert_eq!(engine.last_play, view.last_play);
+                assert_eq!(engine.winners, view.winners);
+                assert_eq!(engine.discard_pile(), view.discard_pile());
+                assert_eq!(engine.turn, view.turn);
+            }
+            // Pass now and then to keep tricks going.
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            dealer.actuate(&format!("P {}", moves[i * 7 % moves.len()]));
+            if dealer.game().is_none() {
+                break
+            }
+        }
+    }
 }
@@ -1,4 +1,5 @@
-use std::collections::BTreeSet;
+use std::fmt;
+use std::error;
 
 use game;
 
@@ -35,42 +36,121 @@ impl State {
 
 #[derive(Debug)]
 pub enum ServerInput {
-    You(game::PlayerNum),
+    Tables(Vec<TableInfo>),
+    Joined(TableInfo),
+    /// The player's number, and the token to get the seat back with.
+    You(game::PlayerNum, String),
+    Rules(game::RuleSet),
     Deal(Vec<game::Card>),
     Play(game::PlayerNum, game::Cards),
     Turn(game::Turn),
     Win(game::PlayerNum),
+    Counts(Vec<usize>),
     End(Vec<game::PlayerNum>),
+    Score(usize, Vec<usize>),
+    MatchEnd(Vec<game::PlayerNum>),
     InvalidInput(String),
     Error(String),
 }
 
+/// A table in the server's lobby.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableInfo {
+    pub name: String,
+    /// How many players have joined so far.
+    pub joined: usize,
+    pub players: usize,
+}
+
+impl fmt::Display for TableInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}/{}", self.name, self.joined, self.players)
+    }
+}
+
+/// Where a player sits down.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Seat {
+    /// At the table with this name.
+    Table(String),
+    /// Back at the seat that was given this token.
+    Back(String),
+}
+
+/// A line from the server that does not follow the protocol.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ProtocolError {
+    NoArgs,
+    Args(char),
+    Score(String),
+    Count(String),
+    TurnType(String),
+    Table(String),
+    PlayerNum,
+    Command,
+    Cards(game::ParseError),
+    Rules(game::RulesError),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtocolError::NoArgs => write!(f, "no args"),
+            ProtocolError::Args(c) => write!(f, "invalid args for {}", c),
+            ProtocolError::Score(ref s) => write!(f, "invalid score {}", s),
+            ProtocolError::Count(ref s) => write!(f, "invalid count {}", s),
+            ProtocolError::TurnType(ref t) => {
+                write!(f, "invalid turn type {}", t)
+            }
+            ProtocolError::Table(ref t) => write!(f, "invalid table {}", t),
+            ProtocolError::PlayerNum => write!(f, "invalid player number"),
+            ProtocolError::Command => write!(f, "invalid input"),
+            ProtocolError::Cards(e) => write!(f, "{}", e),
+            ProtocolError::Rules(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for ProtocolError {}
+
+impl From<game::ParseError> for ProtocolError {
+    fn from(e: game::ParseError) -> ProtocolError {
+        ProtocolError::Cards(e)
+    }
+}
+
+impl From<game::RulesError> for ProtocolError {
+    fn from(e: game::RulesError) -> ProtocolError {
+        ProtocolError::Rules(e)
+    }
+}
+
 pub enum UserInput {
     Play(game::Cards),
 }
 
 pub enum ServerOutput {
+    Join(String),
+    Back(String),
     Game,
     Play(game::Cards),
 }
 
 pub struct Player {
     state: State,
-    num: game::PlayerNum,
-    hand: Vec<game::Card>,
-    turn: Option<game::Turn>,
-    last_play: Option<(game::PlayerNum, game::Cards)>,
-    played: Option<game::Cards>,
+    seat: Seat,
+    view: game::PlayerView,
+    /// How many plays in a row the server has turned down.
+    rejections: usize,
 }
 
-pub fn new() -> Player {
+/// A player who will take `seat`.
+pub fn new(seat: Seat) -> Player {
     Player {
         state: State::Start,
-        num: 0,
-        hand: vec![],
-        turn: None,
-        last_play: None,
-        played: None,
+        seat,
+        view: game::PlayerView::new(0, game::RuleSet::default(), vec![]),
+        rejections: 0,
     }
 }
 
@@ -82,49 +162,82 @@ impl Player {
             return (None, self.state.status())
         }
         let (new_state, output) = match self.state {
-            State::Start => (State::WaitForId, None),
+            State::Start => {
+                let output = match self.seat {
+                    Seat::Table(ref table) => ServerOutput::Join(table.clone()),
+                    Seat::Back(ref token) => ServerOutput::Back(token.clone()),
+                };
+                (State::WaitForId, Some(output))
+            }
             State::WaitForId => {
-                let input = s_inp.unwrap();
-                if let ServerInput::You(p) = input {
-                    self.num = p;
-                    (State::WaitForCards, Some(ServerOutput::Game))
-                } else {
-                    panic!("expected input You")
+                match s_inp.unwrap() {
+                    ServerInput::You(p, token) => {
+                        self.view.player = p;
+                        self.seat = Seat::Back(token);
+                        (State::WaitForCards, Some(ServerOutput::Game))
+                    }
+                    ServerInput::Joined(_) => (State::WaitForId, None),
+                    // The table can't be joined.
+                    ServerInput::InvalidInput(_) => (State::End, None),
+                    _ => panic!("expected input You"),
                 }
             }
             State::WaitForCards => {
                 let input = s_inp.unwrap();
-                if let ServerInput::Deal(mut cards) = input {
-                    cards.sort();
-                    self.hand = cards;
-                    (State::Game, None)
-                } else {
-                    panic!("expected input Deal")
+                match input {
+                    ServerInput::Rules(rules) => {
+                        self.view.rules = rules;
+                        (State::WaitForCards, None)
+                    }
+                    ServerInput::Deal(cards) => {
+                        self.deal(cards);
+                        (State::Game, None)
+                    }
+                    _ => panic!("expected input Deal")
                 }
             }
             State::Game => {
                 let input = s_inp.unwrap();
                 match input {
                     ServerInput::Play(p, cards) => {
-                        if !cards.is_pass() {
-                            self.last_play = Some((p, cards));
-                        }
+                        self.add_play(p, cards);
                         (State::Game, None)
                     }
                     ServerInput::Turn(turn) => {
-                        self.turn = Some(turn);
-                        match self.turn.unwrap() {
+                        self.view.set_turn(turn);
+                        match turn {
                             game::Turn::Start(p)
                                 | game::Turn::Follow(p)
                                 | game::Turn::Any(p)
-                                if p == self.num => {
+                                if p == self.view.player => {
                                 (State::MyTurn, None)
                             }
                             _ => (State::Game, None),
                         }
                     }
-                    ServerInput::Win(_) => (State::Game, None),
-                    ServerInput::End(_) => (State::End, None),
+                    ServerInput::Win(p) => {
+                        self.view.add_win(p);
+                        (State::Game, None)
+                    }
+                    ServerInput::Counts(counts) => {
+                        self.view.card_counts = counts;
+                        (State::Game, None)
+                    }
+                    ServerInput::End(_) => {
+                        self.view.set_turn(game::Turn::End);
+                        (State::Game, None)
+                    }
+                    ServerInput::Score(..) => (State::Game, None),
+                    ServerInput::Rules(rules) => {
+                        self.view.rules = rules;
+                        (State::Game, None)
+                    }
+                    ServerInput::Deal(cards) => {
+                        // A new round of the match.
+                        self.deal(cards);
+                        (State::Game, None)
+                    }
+                    ServerInput::MatchEnd(_) => (State::End, None),
                     _ => panic!("unexpected input: {:?}", input)
                 }
             }
@@ -132,7 +245,6 @@ impl Player {
                 let input = u_inp.unwrap();
                 match input {
                     UserInput::Play(cards) => {
-                        self.played = Some(cards.clone());
                         (State::MyPlay,
                          Some(ServerOutput::Play(cards)))
                     }
@@ -142,16 +254,14 @@ impl Player {
                 let input = s_inp.unwrap();
                 match input {
                     ServerInput::Play(p, cards) => {
-                        self.last_play = Some((p, cards));
-                        let mut curr_cards: BTreeSet<game::Card> =
-                            (&self.hand).iter().cloned().collect();
-                        for card in &self.played.take().unwrap() {
-                            curr_cards.remove(&card);
-                        }
-                        self.hand = curr_cards.into_iter().collect();
+                        self.add_play(p, cards);
+                        self.rejections = 0;
                         (State::Game, None)
                     }
-                    ServerInput::InvalidInput(_) => (State::MyTurn, None),
+                    ServerInput::InvalidInput(_) => {
+                        self.rejections += 1;
+                        (State::MyTurn, None)
+                    }
                     _ => panic!("unexpected input: {:?}", input)
                 }
             }
@@ -161,63 +271,150 @@ impl Player {
         (output, self.state.status())
     }
 
+    fn add_play(&mut self, p: game::PlayerNum, cards: game::Cards) {
+        if let Err(e) = self.view.add_play(p, cards) {
+            panic!("invalid play by #{}: {}", p, e)
+        }
+    }
+
+    fn deal(&mut self, cards: Vec<game::Card>) {
+        let rules = self.view.rules.clone();
+        self.view = game::PlayerView::new(self.view.player, rules, cards);
+    }
+
+    /// The token to get this player's seat back with, once they have one.
+    pub fn token(&self) -> Option<&str> {
+        match self.seat {
+            Seat::Back(ref token) => Some(token),
+            Seat::Table(_) => None,
+        }
+    }
+
+    /// Everything this player has been told about the game so far.
+    pub fn view(&self) -> &game::PlayerView {
+        &self.view
+    }
+
+    /// How many plays in a row the server has turned down.
+    pub fn rejections(&self) -> usize {
+        self.rejections
+    }
+
+    pub fn rules(&self) -> &game::RuleSet {
+        &self.view.rules
+    }
+
     pub fn last_play(&self) -> &Option<(game::PlayerNum, game::Cards)> {
-        &self.last_play
+        &self.view.last_play
     }
 
     pub fn hand(&self) -> &Vec<game::Card> {
-        &self.hand
+        &self.view.hand
     }
 
+    /// What can be played now, without passing.
     pub fn hints(&self) -> Vec<game::Cards> {
-        let mut compare = None;
-        let mut start = false;
-        match self.turn.unwrap() {
-            game::Turn::Start(p) => {
-                if p == self.num {
-                    start = true;
-                } else {
-                    return vec![];
-                }
-            }
-            game::Turn::Follow(_) => {
-                compare = Some(&self.last_play.as_ref().unwrap().1);
+        let mut hints = self.view.legal_moves();
+        hints.retain(|c| !c.is_pass());
+        hints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_view_as_engine() {
+        let rules: game::RuleSet = "players=3 deal=13".parse().unwrap();
+        let mut game = game::Game::with_seed(rules.clone(), 21);
+        let mut player = new(Seat::Table("main".into()));
+        player.actuate(None, None);
+        player.actuate(Some(ServerInput::You(2, "k".into())), None);
+        player.actuate(Some(ServerInput::Rules(rules)), None);
+        player.actuate(Some(ServerInput::Deal(game.hand(2))), None);
+        player.actuate(Some(ServerInput::Turn(game.turn())), None);
+        assert_eq!(&game.view(2), player.view());
+        while game.turn() != game::Turn::End {
+            let p = game.turn().player();
+            let cards = game.legal_moves(p)[0].clone();
+            if p == 2 {
+                assert_eq!(game.legal_moves(p), player.view().legal_moves());
+                player.actuate(None, Some(UserInput::Play(cards.clone())));
             }
-            game::Turn::Any(p) => {
-                if p != self.num {
-                    return vec![];
-                }
+            let wins = game.play(&cards).unwrap();
+            player.actuate(Some(ServerInput::Play(p, cards)), None);
+            if wins {
+                player.actuate(Some(ServerInput::Win(p)), None);
             }
-            game::Turn::End => unreachable!(),
+            let counts = ServerInput::Counts(game.card_counts());
+            player.actuate(Some(counts), None);
+            let input = match game.turn() {
+                game::Turn::End => ServerInput::End(game.winners()),
+                turn => ServerInput::Turn(turn),
+            };
+            player.actuate(Some(input), None);
+            assert_eq!(&game.view(2), player.view());
         }
-        let mut hints = vec![];
-        for mask in 1u32..(1<<self.hand.len()) {
-            if let Some(ref cards) = compare {
-                if mask.count_ones() != (cards.len() as u32) {
-                    continue
-                }
-            }
-            let mut cards = vec![];
-            for i in 0..self.hand.len() {
-                if mask&(1<<i) == 0 {
-                    continue
-                }
-                cards.push(self.hand[i]);
-            }
-            if start && !cards.contains(&game::LOWEST_CARD) {
-                continue
-            }
-            if let Ok(hint) = game::Cards::new(cards) {
-                if let Some(compare) = compare {
-                    if hint > *compare {
-                        hints.push(hint);
-                    }
-                } else {
-                    hints.push(hint);
-                }
-            }
+    }
+
+    #[test]
+    fn rejections() {
+        let rules: game::RuleSet = "players=2 deal=13".parse().unwrap();
+        let game = game::Game::with_seed(rules.clone(), 4);
+        let p = game.turn().player();
+        let mut player = new(Seat::Table("main".into()));
+        player.actuate(None, None);
+        player.actuate(Some(ServerInput::You(p, "k".into())), None);
+        player.actuate(Some(ServerInput::Rules(rules)), None);
+        player.actuate(Some(ServerInput::Deal(game.hand(p))), None);
+        player.actuate(Some(ServerInput::Turn(game.turn())), None);
+        player.actuate(None, Some(UserInput::Play(game::Cards::pass())));
+        let invalid = ServerInput::InvalidInput("invalid play".into());
+        player.actuate(Some(invalid), None);
+        assert_eq!(1, player.rejections());
+        let cards = player.view().fallback_move().unwrap();
+        player.actuate(None, Some(UserInput::Play(cards.clone())));
+        player.actuate(Some(ServerInput::Play(p, cards)), None);
+        assert_eq!(0, player.rejections());
+    }
+
+    #[test]
+    fn resumed_counts() {
+        let rules: game::RuleSet = "players=2 deal=13".parse().unwrap();
+        let hand = |s: &str| -> Vec<game::Card> {
+            s.split_whitespace().map(|c| c.parse().unwrap()).collect()
+        };
+        let hands = vec![
+            hand("3D 3H 3S 4C 4H 4S 5C 5D 5S 6C 6D 6H 7D"),
+            hand("3C 4D 5H 6S 7C 8D 9H TS JC QD KS KH AC"),
+        ];
+        let mut game = game::Game::from_hands(rules.clone(), hands.clone())
+            .unwrap();
+        let plays = ["3C 4D 5H 6S 7C", "", "8D 9H TS JC QD"];
+        for cards in &plays {
+            game.play(&cards.parse().unwrap()).unwrap();
         }
-        hints.sort();
-        hints
+        // #2 comes back and is told their deal and every play since.
+        let mut player = new(Seat::Back("k".into()));
+        player.actuate(None, None);
+        player.actuate(Some(ServerInput::You(2, "k".into())), None);
+        player.actuate(Some(ServerInput::Rules(rules)), None);
+        player.actuate(Some(ServerInput::Deal(hands[1].clone())), None);
+        player.actuate(Some(ServerInput::Counts(vec![13, 13])), None);
+        for cards in &[plays[0], plays[2]] {
+            let cards = cards.parse().unwrap();
+            player.actuate(Some(ServerInput::Play(2, cards)), None);
+        }
+        player.actuate(Some(ServerInput::Counts(game.card_counts())), None);
+        player.actuate(Some(ServerInput::Turn(game.turn())), None);
+        assert_eq!(game.view(2).hand, player.view().hand);
+        assert_eq!(game.card_counts(), player.view().card_counts);
+        // Nobody plays more cards than they are known to hold.
+        let mut view = player.view().clone();
+        view.card_counts[0] = 2;
+        assert_eq!(Err(game::GameError::NotInHand),
+                   view.add_play(1, "3D 3H 3S".parse().unwrap()));
+        assert_eq!(2, view.card_counts[0]);
     }
 }
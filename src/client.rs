@@ -4,23 +4,152 @@ use std::io;
 use std::io::prelude::*;
 use std::net::TcpStream;
 use std::str::FromStr;
+use std::fmt;
 
-use bots::player::{self, Status, ServerInput, UserInput, ServerOutput};
-use game;
+use bots::player::{self, Status, ServerInput, UserInput, ServerOutput,
+                   ProtocolError};
+pub use bots::player::{Seat, TableInfo};
+use rand;
+
+use game::{self, PlayerView};
+use game::record::Record;
+use game::save::parse_cards;
+use game::solver::{self, Solver};
+use server;
+use strategy::{greedy, Strategy};
 use utils;
 
-pub fn play(host: String) {
-    let (player, channel) = run_player(host);
+/// How many cards may be left in all hands for hints to be searched.
+const HINT_CARDS: usize = 20;
+/// How many ways of dealing the unseen cards hints are searched with.
+const HINT_DEALS: usize = 20;
+/// The search nodes allowed for each hint and deal.
+const HINT_LIMIT: usize = 20_000;
+
+pub fn play(host: String, seat: Seat) {
+    let (player, channel) = run_player(host, seat);
     interact(player, channel);
 }
 
-pub fn bot(host: String) {
-    let (player, channel) = run_player(host);
-    greedy_bot(player, channel);
+pub fn bot(host: String, seat: Seat) {
+    bot_with(host, seat, Box::new(greedy::Greedy))
+}
+
+/// Plays with `strategy` until the match is over.
+pub fn bot_with(host: String, seat: Seat, strategy: Box<dyn Strategy>) {
+    let (player, channel) = run_player(host, seat);
+    run_strategy(strategy, player, channel);
+}
+
+/// The tables in the lobby of `host`.
+pub fn tables(host: &str) -> io::Result<Vec<TableInfo>> {
+    let mut stream = connect(host)?;
+    stream.write_all(b"L\r\n")?;
+    let line = utils::try_read_line(&stream).ok_or(io::ErrorKind::UnexpectedEof)?;
+    match line.trim().parse() {
+        Ok(ServerInput::Tables(tables)) => Ok(tables),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData,
+                                "invalid server response")),
+    }
+}
+
+/// Steps through a recorded game, turn by turn.
+pub fn replay(record: Record) {
+    println!("Played on {}.", record.date);
+    for (i, name) in record.players.iter().enumerate() {
+        println!("Player #{} was {}.", i+1, name);
+    }
+    print_server_input(&ServerInput::Rules(record.game.rules().clone()));
+    let moves = record.game.moves();
+    let mut n = 0;
+    let mut reveal = false;
+    print_position(&record.game, n, reveal);
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        match line.trim() {
+            "" | "next" => {
+                if n == moves.len() {
+                    println!("That was the last move.");
+                    continue;
+                }
+                n += 1;
+            }
+            "back" => {
+                if n == 0 {
+                    println!("That was the deal.");
+                    continue;
+                }
+                n -= 1;
+            }
+            "hands" => reveal = !reveal,
+            "quit" => break,
+            "help" => {
+                print_replay_usage();
+                continue;
+            }
+            token => match token.parse() {
+                Ok(m) if m <= moves.len() => n = m,
+                Ok(_) => {
+                    println!("There are only {} moves.", moves.len());
+                    continue;
+                }
+                Err(_) => {
+                    println!("Invalid input.");
+                    print_replay_usage();
+                    continue;
+                }
+            },
+        }
+        print_position(&record.game, n, reveal);
+    }
+}
+
+/// Shows `game` after its first `n` moves, as if they had just been played.
+fn print_position(game: &game::Game, n: usize, reveal: bool) {
+    let position = game.replay(n);
+    if n == 0 {
+        println!("{}The deal{}", style::Bold, style::Reset);
+    } else {
+        let (p, ref cards) = game.moves()[n-1];
+        print!("{}{}.{} ", style::Bold, n, style::Reset);
+        print_server_input(&ServerInput::Play(p, cards.clone()));
+        if !cards.is_pass() && position.hand_bits(p).is_empty() {
+            print_server_input(&ServerInput::Win(p));
+        }
+    }
+    print_server_input(&ServerInput::Counts(position.card_counts()));
+    if reveal {
+        for p in 1..position.players()+1 {
+            print!("#{} ", p);
+            pp_cards(position.hand(p));
+            println!();
+        }
+    }
+    match position.turn() {
+        game::Turn::End => {
+            print_server_input(&ServerInput::End(position.winners()))
+        }
+        turn => print_server_input(&ServerInput::Turn(turn)),
+    }
+}
+
+fn print_replay_usage() {
+    println!("Usage:
+{bold}help{reset} - print this
+{bold}next{reset} - go to the next move, also by pressing enter
+{bold}back{reset} - go back a move
+{bold}N{reset} - go to move N, or to the deal if N is 0
+{bold}hands{reset} - show or hide everyone's cards
+{bold}quit{reset} - stop the replay",
+             bold = style::Bold,
+             reset = style::Reset,
+    );
 }
 
-fn run_player(host: String) -> (Arc<Mutex<player::Player>>, Channel) {
-    let player = Arc::new(Mutex::new(player::new()));
+fn run_player(host: String, seat: Seat)
+              -> (Arc<Mutex<player::Player>>, Channel) {
+    let player = Arc::new(Mutex::new(player::new(seat)));
     let channel = Channel::new();
     {
         let player = player.clone();
@@ -114,7 +243,9 @@ fn interact(player: Arc<Mutex<player::Player>>, mut channel: Channel) {
                         println!("Invalid hint index.");
                     }
                 } else {
-                    match tokens[1].trim().parse() {
+                    let rules = player.lock().unwrap().rules().clone();
+                    let cards: Vec<_> = tokens[1].split_whitespace().collect();
+                    match parse_cards(&cards, &rules) {
                         Ok(cards) => {
                             channel.play_cards(cards);
                         }
@@ -136,8 +267,8 @@ fn interact(player: Arc<Mutex<player::Player>>, mut channel: Channel) {
                 let last_play = player.last_play();
                 if let Some((p, ref cards)) = *last_play {
                     print!("Player #{} played ", p);
-                    pp_cards(cards);
-                    println!("");
+                    pp_play(cards);
+                    println!();
                 } else {
                     println!("No one has played yet.");
                 }
@@ -150,20 +281,59 @@ fn interact(player: Arc<Mutex<player::Player>>, mut channel: Channel) {
                 } else {
                     print!("You have ");
                     pp_cards(hand.iter().cloned());
-                    println!("");
+                    println!();
                 }
             }
+            "rules" => {
+                println!("{}", player.lock().unwrap().rules());
+            }
             "hint" => {
-                hints = player.lock().unwrap().hints();
-                if hints.is_empty() {
+                let by_chance = match tokens.get(1).map(|t| t.trim()) {
+                    None => false,
+                    Some("odds") => true,
+                    Some(_) => {
+                        println!("Invalid input.");
+                        print_usage();
+                        continue;
+                    }
+                };
+                let view = player.lock().unwrap().view().clone();
+                let found = player.lock().unwrap().hints();
+                if found.is_empty() {
+                    hints = found;
                     println!("You can't play anything.");
-                } else {
-                    println!("Hints:");
-                    for (i, cards) in hints.iter().enumerate() {
+                    continue;
+                }
+                let chances = hint_chances(&view, &found);
+                if by_chance && chances.is_none() {
+                    println!("Too many cards left to tell the odds.");
+                }
+                let sorted = by_chance && chances.is_some();
+                let annotated = annotate(&view, found, chances, sorted);
+                hints = annotated.iter().map(|h| h.cards.clone()).collect();
+                println!("Hints:");
+                let mut kind = None;
+                for (i, hint) in annotated.iter().enumerate() {
+                    let name = hint.cards.kind().map(|k| k.name());
+                    if sorted {
+                        print!("{:>3}: ", i+1);
+                        pp_play(&hint.cards);
+                    } else {
+                        if name != kind {
+                            println!("{}{}:{}", style::Bold, name.unwrap(),
+                                     style::Reset);
+                            kind = name;
+                        }
                         print!("{:>3}: ", i+1);
-                        pp_cards(cards);
-                        println!("");
+                        pp_cards(&hint.cards);
                     }
+                    if let Some(chance) = hint.chance {
+                        print!(" {:>3.0}%", chance*100.0);
+                    }
+                    if let Some(label) = hint.label {
+                        print!(" {}{}{}", style::Bold, label, style::Reset);
+                    }
+                    println!();
                 }
             }
             "help" => {
@@ -185,37 +355,120 @@ fn print_usage() {
 {bold}pass{reset} - pass
 {bold}last{reset} - show last played
 {bold}hand{reset} - show cards in your hand
+{bold}rules{reset} - show the house rules
 {bold}hint{reset} - give hints on what can be played
+{bold}hint odds{reset} - give hints, most likely to win first
 {bold}play [N]{reset} - where N is the number of the hint",
              bold = style::Bold,
              reset = style::Reset,
     );
 }
 
-fn greedy_bot(player: Arc<Mutex<player::Player>>, mut channel: Channel) {
+/// How likely each of `hints` is to let the player go out first, once few
+/// enough cards are left to search.
+fn hint_chances(view: &PlayerView, hints: &[game::Cards])
+                -> Option<solver::Chances> {
+    let left: usize = view.card_counts.iter().sum();
+    if left > HINT_CARDS {
+        return None
+    }
+    let mut solver = Solver::new(HINT_LIMIT);
+    solver::win_chances(view, hints, HINT_DEALS, &mut solver,
+                        &mut rand::thread_rng())
+}
+
+/// A hint, with how likely it is to win and what that means, when known.
+struct Hint {
+    cards: game::Cards,
+    chance: Option<f64>,
+    label: Option<&'static str>,
+}
+
+/// Puts `chances` with `hints`, most likely to win first if `by_chance`.
+/// A win is only guaranteed when the other hands are known, and a play
+/// loses control when an opponent may beat it.
+fn annotate(view: &PlayerView, hints: Vec<game::Cards>,
+            chances: Option<solver::Chances>, by_chance: bool) -> Vec<Hint> {
+    let chances = match chances {
+        Some(chances) => chances,
+        None => {
+            return hints.into_iter()
+                .map(|cards| Hint { cards, chance: None, label: None })
+                .collect()
+        }
+    };
+    let exact = chances.exact;
+    let best = chances.wins.iter().cloned().fold(0.0, f64::max);
+    let mut annotated: Vec<_> = hints.into_iter().zip(chances.wins)
+        .map(|(cards, chance)| {
+            let goes_out = cards.len() == view.hand.len();
+            let label = if exact && chance == 1.0 {
+                Some("guaranteed win")
+            } else if best > 0.0 && chance == best {
+                Some("best")
+            } else if !goes_out && view.can_be_beaten(&cards) {
+                Some("loses control")
+            } else {
+                None
+            };
+            Hint { cards, chance: Some(chance), label }
+        })
+        .collect();
+    if by_chance {
+        annotated.sort_by(|a, b| b.chance.partial_cmp(&a.chance).unwrap());
+    }
+    annotated
+}
+
+fn run_strategy(mut strategy: Box<dyn Strategy>,
+                player: Arc<Mutex<player::Player>>, mut channel: Channel) {
     loop {
         if !channel.wait_to_play() {
             break
         }
-        let hints = player.lock().unwrap().hints();
-        if hints.is_empty() {
-            channel.play_cards("".parse().unwrap());
-        } else {
-            channel.play_cards(hints[0].clone());
+        let (view, rejected) = {
+            let player = player.lock().unwrap();
+            (player.view().clone(), player.rejections() > 0)
+        };
+        // Once a play was turned down, choosing again would only repeat it.
+        let cards = match view.fallback_move() {
+            Some(cards) if rejected => cards,
+            _ => strategy.play(&view),
+        };
+        channel.play_cards(cards);
+    }
+}
+
+/// Connects to `host`, given as a name or address with or without a port.
+fn connect(host: &str) -> io::Result<TcpStream> {
+    if let Some(i) = host.rfind(':') {
+        if let Ok(port) = host[i+1..].parse() {
+            let name = host[..i].trim_start_matches('[').trim_end_matches(']');
+            return TcpStream::connect((name, port))
         }
     }
+    TcpStream::connect((host, server::DEFAULT_PORT))
 }
 
 fn run(host: String, player: Arc<Mutex<player::Player>>, mut channel: Channel) {
-    let mut stream = TcpStream::connect((&*host, 2222)).expect("connection failed");
+    let mut stream = connect(&host).expect("connection failed");
     let mut status = None;
+    let mut lost = false;
     loop {
         let (mut s_inp, mut u_inp) = (None, None);
         if let Some(s) = status {
             match s {
                 Status::ServerInput => {
-                    let inp = utils::read_line(&stream);
-                    let inp = inp.trim().parse().expect("invalid server response");
+                    let inp = match utils::try_read_line(&stream) {
+                        Some(inp) => inp,
+                        None => {
+                            lost = true;
+                            break
+                        }
+                    };
+                    let rules = player.lock().unwrap().rules().clone();
+                    let inp = parse_server_input(inp.trim(), &rules)
+                        .expect("invalid server response");
                     print_server_input(&inp);
                     s_inp = Some(inp);
                 }
@@ -234,93 +487,177 @@ fn run(host: String, player: Arc<Mutex<player::Player>>, mut channel: Channel) {
             status = Some(res.1);
         }
         if let Some(output) = output {
-            stream.write((output.to_string() + "\r\n").as_bytes()).expect("write error");
+            let line = output.to_string() + "\r\n";
+            if stream.write_all(line.as_bytes()).is_err() {
+                lost = true;
+                break
+            }
+        }
+    }
+    if lost {
+        println!("Lost the connection to the server.");
+        if let Some(token) = player.lock().unwrap().token() {
+            println!("Take your seat back with --rejoin={}.", token);
         }
     }
     channel.has_ended();
 }
 
 impl FromStr for ServerInput {
-    type Err = String;
+    type Err = ProtocolError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let tokens: Vec<_> = s.splitn(2, ' ').collect();
-        if tokens.len() != 2 {
-            return Err("no args".into())
+        parse_server_input(s, &game::RuleSet::default())
+    }
+}
+
+/// Reads a line from the server, with any cards played valued under
+/// `rules`.
+fn parse_server_input(s: &str, rules: &game::RuleSet)
+                      -> Result<ServerInput, ProtocolError> {
+    if s == "L" {
+        // No tables.
+        return Ok(ServerInput::Tables(vec![]))
+    }
+    let tokens: Vec<_> = s.splitn(2, ' ').collect();
+    if tokens.len() != 2 {
+        return Err(ProtocolError::NoArgs)
+    }
+    match tokens[0] {
+        "L" => {
+            let args: Vec<_> = tokens[1].split_whitespace().collect();
+            let tables = args.chunks(2).map(parse_table)
+                .collect::<Result<_, _>>()?;
+            Ok(ServerInput::Tables(tables))
         }
-        match tokens[0] {
-            "U" => {
-                Ok(ServerInput::You(parse_player_num(tokens[1])?))
-            }
-            "D" => {
-                let mut hand = vec![];
-                for s in tokens[1].split_whitespace() {
-                    hand.push(s.parse()?);
+        "J" => {
+            let args: Vec<_> = tokens[1].split_whitespace().collect();
+            Ok(ServerInput::Joined(parse_table(&args)?))
+        }
+        "U" => {
+            let args: Vec<_> = tokens[1].split_whitespace().collect();
+            match args[..] {
+                [p, token] => {
+                    Ok(ServerInput::You(parse_player_num(p)?, token.into()))
                 }
-                Ok(ServerInput::Deal(hand))
+                _ => Err(ProtocolError::Args('U')),
             }
-            "P" => {
-                let args: Vec<_> = tokens[1].splitn(2, ' ').collect();
-                if args.len() <= 2 {
-                    let p = parse_player_num(args[0])?;
-                    let cards_str = if args.len() == 2 { args[1] } else { "" };
-                    let cards = cards_str.parse()?;
-                    return Ok(ServerInput::Play(p, cards))
-                }
-                Err("invalid args for P".into())
+        }
+        "R" => {
+            Ok(ServerInput::Rules(tokens[1].parse()?))
+        }
+        "D" => {
+            let mut hand = vec![];
+            for s in tokens[1].split_whitespace() {
+                hand.push(s.parse()?);
             }
-            "T" => {
-                let args: Vec<_> = tokens[1].split_whitespace().collect();
-                if args.len() == 2 {
-                    let p = parse_player_num(args[0])?;
-                    let turn = match args[1] {
-                        "S" => game::Turn::Start(p),
-                        "F" => game::Turn::Follow(p),
-                        "A" => game::Turn::Any(p),
-                        _ => return Err(format!("invalid turn type {}", args[1]))
-                    };
-                    return Ok(ServerInput::Turn(turn))
-                }
-                Err("invalid args for T".into())
+            Ok(ServerInput::Deal(hand))
+        }
+        "P" => {
+            let args: Vec<_> = tokens[1].splitn(2, ' ').collect();
+            if args.len() <= 2 {
+                let p = parse_player_num(args[0])?;
+                let cards_str = if args.len() == 2 { args[1] } else { "" };
+                let cards: Vec<_> = cards_str.split_whitespace().collect();
+                let cards = parse_cards(&cards, rules)?;
+                return Ok(ServerInput::Play(p, cards))
             }
-            "W" => {
-                Ok(ServerInput::Win(parse_player_num(tokens[1])?))
+            Err(ProtocolError::Args('P'))
+        }
+        "T" => {
+            let args: Vec<_> = tokens[1].split_whitespace().collect();
+            if args.len() == 2 {
+                let p = parse_player_num(args[0])?;
+                let turn = match args[1] {
+                    "S" => game::Turn::Start(p),
+                    "F" => game::Turn::Follow(p),
+                    "A" => game::Turn::Any(p),
+                    _ => return Err(ProtocolError::TurnType(args[1].into()))
+                };
+                return Ok(ServerInput::Turn(turn))
             }
-            "E" => {
-                let args: Vec<_> = tokens[1].split_whitespace().collect();
-                let mut winners = vec![];
-                for arg in args {
-                    winners.push(parse_player_num(arg)?);
-                }
-                Ok(ServerInput::End(winners))
+            Err(ProtocolError::Args('T'))
+        }
+        "W" => {
+            Ok(ServerInput::Win(parse_player_num(tokens[1])?))
+        }
+        "C" => {
+            let mut counts = vec![];
+            for arg in tokens[1].split_whitespace() {
+                let n = arg.parse()
+                    .map_err(|_| ProtocolError::Count(arg.into()))?;
+                counts.push(n);
             }
-            "?" => {
-                Ok(ServerInput::InvalidInput(tokens[1].to_string()))
+            Ok(ServerInput::Counts(counts))
+        }
+        "E" => {
+            let args: Vec<_> = tokens[1].split_whitespace().collect();
+            let mut winners = vec![];
+            for arg in args {
+                winners.push(parse_player_num(arg)?);
             }
-            "!" => {
-                Ok(ServerInput::Error(tokens[1].to_string()))
+            Ok(ServerInput::End(winners))
+        }
+        "S" => {
+            let mut args = tokens[1].split_whitespace().map(|a| {
+                a.parse().map_err(|_| ProtocolError::Score(a.into()))
+            });
+            let round = args.next().ok_or(ProtocolError::Args('S'))??;
+            let scores = args.collect::<Result<_, _>>()?;
+            Ok(ServerInput::Score(round, scores))
+        }
+        "M" => {
+            let mut ranking = vec![];
+            for arg in tokens[1].split_whitespace() {
+                ranking.push(parse_player_num(arg)?);
             }
-            _ => Err("invalid input".into())
+            Ok(ServerInput::MatchEnd(ranking))
+        }
+        "?" => {
+            Ok(ServerInput::InvalidInput(tokens[1].to_string()))
         }
+        "!" => {
+            Ok(ServerInput::Error(tokens[1].to_string()))
+        }
+        _ => Err(ProtocolError::Command)
+    }
+}
+
+/// Reads `{T} {J}/{N}`.
+fn parse_table(args: &[&str]) -> Result<TableInfo, ProtocolError> {
+    let invalid = || ProtocolError::Table(args.join(" "));
+    if args.len() != 2 {
+        return Err(invalid())
+    }
+    let counts: Vec<_> = args[1].splitn(2, '/').map(|n| n.parse()).collect();
+    match counts[..] {
+        [Ok(joined), Ok(players)] => Ok(TableInfo {
+            name: args[0].into(),
+            joined,
+            players,
+        }),
+        _ => Err(invalid()),
     }
 }
 
-fn parse_player_num(s: &str) -> Result<game::PlayerNum, String> {
+fn parse_player_num(s: &str) -> Result<game::PlayerNum, ProtocolError> {
     let bytes = s.as_bytes();
-    if bytes.len() == 2 && bytes[0] == ('#' as u8) {
-        let num = bytes[1] - ('0' as u8);
-        if 1 <= num && num <= 4 {
+    if bytes.len() == 2 && bytes[0] == b'#' {
+        let num = bytes[1].wrapping_sub(b'0');
+        if (1..=game::MAX_PLAYERS as u8).contains(&num) {
             return Ok(num.into())
         }
     }
-    Err("invalid player number".into())
+    Err(ProtocolError::PlayerNum)
 }
 
-impl ToString for ServerOutput {
-    fn to_string(&self) -> String {
+impl fmt::Display for ServerOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ServerOutput::Game => "G".to_string(),
+            ServerOutput::Join(ref table) => write!(f, "J {}", table),
+            ServerOutput::Back(ref token) => write!(f, "B {}", token),
+            ServerOutput::Game => write!(f, "G"),
             ServerOutput::Play(ref cards) => {
-                format!("P {}", cards)
+                write!(f, "P {}", cards)
             }
         }
     }
@@ -330,13 +667,26 @@ use termion::{color, style};
 
 fn print_server_input(inp: &ServerInput) {
     match *inp {
-        ServerInput::You(p) => {
+        ServerInput::Tables(ref tables) => {
+            for table in tables {
+                println!("Table {}: {} of {} players.", table.name,
+                         table.joined, table.players);
+            }
+        }
+        ServerInput::Joined(ref table) => {
+            println!("You joined table {}, with {} of {} players.",
+                     table.name, table.joined, table.players);
+        }
+        ServerInput::You(p, _) => {
             println!("You are player #{}.", p);
         }
+        ServerInput::Rules(ref rules) => {
+            println!("Rules: {}", rules);
+        }
         ServerInput::Deal(ref cards) => {
             print!("Your cards are ");
             pp_cards(cards.iter().cloned());
-            println!("");
+            println!();
         }
         ServerInput::Turn(turn) => {
             print!("Player #{}'s turn ", turn.player());
@@ -346,7 +696,7 @@ fn print_server_input(inp: &ServerInput) {
                 game::Turn::Any(_) => print!("for control"),
                 game::Turn::End => unreachable!(),
             }
-            println!("");
+            println!();
         }
         ServerInput::Play(p, ref cards) => {
             print!("Player #{} ", p);
@@ -354,18 +704,43 @@ fn print_server_input(inp: &ServerInput) {
                 print!("{}passed{}", style::Bold, style::Reset);
             } else {
                 print!("played ");
-                pp_cards(cards);
+                pp_play(cards);
             }
-            println!("");
+            println!();
         }
         ServerInput::Win(p) => {
             println!("Player #{} won.", p);
         }
+        ServerInput::Counts(ref counts) => {
+            print!("Cards left:");
+            for (i, &n) in counts.iter().enumerate() {
+                if n == 1 {
+                    print!(" {}{}#{} 1{}{}", style::Bold, color::Fg(color::Red),
+                           i+1, color::Fg(color::Reset), style::Reset);
+                } else {
+                    print!(" #{} {}", i+1, n);
+                }
+            }
+            println!();
+        }
         ServerInput::End(ref winners) => {
             println!("Game has ended. Winners are:");
-            println!("1st: #{}", winners[0]);
-            println!("2nd: #{}", winners[1]);
-            println!("3rd: #{}", winners[2]);
+            for (i, w) in winners.iter().enumerate() {
+                println!("{}: #{}", ["1st", "2nd", "3rd"][i], w);
+            }
+        }
+        ServerInput::Score(round, ref scores) => {
+            print!("Penalties after round {}:", round);
+            for (i, s) in scores.iter().enumerate() {
+                print!(" #{} {}", i+1, s);
+            }
+            println!();
+        }
+        ServerInput::MatchEnd(ref ranking) => {
+            println!("Match has ended. Ranking:");
+            for (i, p) in ranking.iter().enumerate() {
+                println!("{}: #{}", ["1st", "2nd", "3rd", "4th"][i], p);
+            }
         }
         ServerInput::InvalidInput(ref msg) => {
             println!("{}Invalid move: {}{}",
@@ -393,6 +768,14 @@ fn pp_cards<T: IntoIterator<Item=game::Card>>(cards: T) {
     print!("{}{}", color::Fg(color::Reset), color::Bg(color::Reset));
 }
 
+/// E.g. "a pair (kings): KC KS".
+fn pp_play(cards: &game::Cards) {
+    if let Some(kind) = cards.kind() {
+        print!("{}: ", kind);
+    }
+    pp_cards(cards);
+}
+
 fn pp_card(card: game::Card) {
     match card.suit {
         'C' | 'S' => print!("{}", color::Fg(color::Black)),
@@ -408,3 +791,58 @@ fn pp_card(card: game::Card) {
         _ => unreachable!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{Game, Hand, RuleSet, Turn};
+
+    #[test]
+    fn annotated_hints() {
+        // Everyone plays their lowest move until someone is about to go
+        // out. With the whole deck dealt to two players, both hands are
+        // known.
+        let rules: RuleSet = "players=2".parse().unwrap();
+        let mut game = Game::with_seed(rules, 8);
+        let (p, out) = loop {
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            if let Some(out) = moves.iter()
+                .find(|c| c.len() == game.hand(p).len()) {
+                break (p, out.clone())
+            }
+            game.play(&moves[0]).unwrap();
+        };
+        assert_ne!(Turn::End, game.turn());
+        let view = game.view(p);
+        let mut hints = view.legal_moves();
+        hints.retain(|c| !c.is_pass());
+        let chances = hint_chances(&view, &hints).unwrap();
+        assert!(chances.exact);
+        let annotated = annotate(&view, hints.clone(), Some(chances.clone()),
+                                 false);
+        // Hints keep their order and are still annotated.
+        for (hint, cards) in annotated.iter().zip(&hints) {
+            assert_eq!(Hand::from(cards), Hand::from(&hint.cards));
+            assert!(hint.chance.is_some());
+        }
+        let i = hints.iter().position(|c| Hand::from(c) == Hand::from(&out))
+            .unwrap();
+        assert_eq!(Some("guaranteed win"), annotated[i].label);
+        // Control is lost when the opponent can follow.
+        for cards in hints.iter().filter(|c| c.len() < view.hand.len()) {
+            let mut next = game.clone();
+            next.play(cards).unwrap();
+            let q = next.turn().player();
+            let follows = next.legal_moves(q).iter().any(|c| !c.is_pass());
+            assert_eq!(follows, view.can_be_beaten(cards));
+        }
+        let sorted = annotate(&view, hints.clone(), Some(chances.clone()),
+                              true);
+        assert_eq!(Some(1.0), sorted[0].chance);
+        // Not guaranteed when the other hands are only guessed at.
+        let guessed = solver::Chances { exact: false, ..chances };
+        let annotated = annotate(&view, hints, Some(guessed), false);
+        assert_eq!(Some("best"), annotated[i].label);
+    }
+}
@@ -0,0 +1,288 @@
+//! Hands as bitboards, and generation of the combinations they can play.
+//!
+//! Each of the 52 cards has its own bit, ordered by rank and then by suit
+//! (in `SUITS` order, regardless of the rules), so a whole hand fits in a
+//! `u64`.
+
+use std::collections::BTreeSet;
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitOr, Sub};
+
+use super::{Card, Cards, RuleSet, RANKS, SUITS};
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Hand(pub u64);
+
+/// The bit of `card`.
+pub fn index(card: Card) -> usize {
+    RANKS.find(card.rank).unwrap()*4 + SUITS.find(card.suit).unwrap()
+}
+
+/// The card of bit `i`.
+pub fn card_at(i: usize) -> Card {
+    Card {
+        rank: RANKS.as_bytes()[i / 4] as char,
+        suit: SUITS.as_bytes()[i % 4] as char,
+    }
+}
+
+impl Hand {
+    pub fn new() -> Hand {
+        Hand(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, card: Card) -> bool {
+        self.0 & (1 << index(card)) != 0
+    }
+
+    pub fn is_superset(&self, other: Hand) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn insert(&mut self, card: Card) {
+        self.0 |= 1 << index(card);
+    }
+
+    pub fn remove(&mut self, card: Card) {
+        self.0 &= !(1 << index(card));
+    }
+
+    /// The suits held of the rank at `rank` in `RANKS`, as the lowest four
+    /// bits.
+    pub fn rank_mask(&self, rank: usize) -> u64 {
+        (self.0 >> (rank*4)) & 0xf
+    }
+
+    /// The cards in bit order.
+    pub fn cards(&self) -> Vec<Card> {
+        let mut cards = Vec::with_capacity(self.len());
+        let mut bits = self.0;
+        while bits != 0 {
+            let i = bits.trailing_zeros() as usize;
+            cards.push(card_at(i));
+            bits &= bits - 1;
+        }
+        cards
+    }
+}
+
+impl FromIterator<Card> for Hand {
+    fn from_iter<T: IntoIterator<Item=Card>>(iter: T) -> Hand {
+        let mut hand = Hand::new();
+        for card in iter {
+            hand.insert(card);
+        }
+        hand
+    }
+}
+
+impl From<&Cards> for Hand {
+    fn from(cards: &Cards) -> Hand {
+        cards.cards().iter().cloned().collect()
+    }
+}
+
+impl BitOr for Hand {
+    type Output = Hand;
+    fn bitor(self, other: Hand) -> Hand {
+        Hand(self.0 | other.0)
+    }
+}
+
+impl BitAnd for Hand {
+    type Output = Hand;
+    fn bitand(self, other: Hand) -> Hand {
+        Hand(self.0 & other.0)
+    }
+}
+
+impl Sub for Hand {
+    type Output = Hand;
+    fn sub(self, other: Hand) -> Hand {
+        Hand(self.0 & !other.0)
+    }
+}
+
+/// Every `k`-card subset of the set bits of `mask`.
+fn subsets(mask: u64, k: u32) -> Vec<u64> {
+    let mut res = vec![];
+    if mask.count_ones() < k {
+        return res
+    }
+    // Walk the subsets of the mask and keep those of the right size.
+    let mut sub = mask;
+    loop {
+        if sub.count_ones() == k {
+            res.push(sub);
+        }
+        if sub == 0 {
+            break
+        }
+        sub = (sub - 1) & mask;
+    }
+    res
+}
+
+/// Every combination in `hand` that can be played on top of `last`, or
+/// every combination at all if there is no `last`. Passing is not
+/// included. The result is sorted.
+pub fn combinations(hand: Hand, rules: &RuleSet, last: Option<&Cards>)
+                    -> Vec<Cards> {
+    let size = last.map(|c| c.len());
+    let wants = |n: usize| size.map_or(true, |s| s == n);
+    let mut masks = vec![];
+
+    for r in 0..13 {
+        let suits = hand.rank_mask(r);
+        for k in 1..4 {
+            if wants(k as usize) {
+                for sub in subsets(suits, k) {
+                    masks.push(sub << (r*4));
+                }
+            }
+        }
+    }
+
+    if wants(5) {
+        let mut five = BTreeSet::new();
+        // Straights, counting ranks from the ace as in `is_straight_start`.
+        for start in 0..13 {
+            if !rules.is_straight_start(start) {
+                continue
+            }
+            let mut partial = vec![0u64];
+            for i in 0..5 {
+                let r = (start + i + 11) % 13; // back to `RANKS` order
+                let suits = hand.rank_mask(r);
+                let mut next = vec![];
+                for &p in &partial {
+                    for s in 0..4 {
+                        if suits & (1 << s) != 0 {
+                            next.push(p | 1 << (r*4 + s));
+                        }
+                    }
+                }
+                partial = next;
+            }
+            five.extend(partial);
+        }
+        // Flushes.
+        for s in 0..4 {
+            let mut suited = 0u64;
+            for r in 0..13 {
+                if hand.rank_mask(r) & (1 << s) != 0 {
+                    suited |= 1 << r;
+                }
+            }
+            for sub in subsets(suited, 5) {
+                let mut mask = 0u64;
+                for r in 0..13 {
+                    if sub & (1 << r) != 0 {
+                        mask |= 1 << (r*4 + s);
+                    }
+                }
+                five.insert(mask);
+            }
+        }
+        // Full houses and four of a kinds.
+        for r in 0..13 {
+            let suits = hand.rank_mask(r);
+            let trios = subsets(suits, 3);
+            let quad = if suits == 0xf { Some(suits << (r*4)) } else { None };
+            if trios.is_empty() {
+                continue
+            }
+            for r2 in (0..13).filter(|&r2| r2 != r) {
+                let suits2 = hand.rank_mask(r2);
+                for &t in &trios {
+                    for p in subsets(suits2, 2) {
+                        five.insert(t << (r*4) | p << (r2*4));
+                    }
+                }
+                if let Some(q) = quad {
+                    for k in subsets(suits2, 1) {
+                        five.insert(q | k << (r2*4));
+                    }
+                }
+            }
+        }
+        masks.extend(five);
+    }
+
+    let mut res: Vec<Cards> = masks.into_iter()
+        .filter_map(|m| Cards::with_rules(Hand(m).cards(), rules).ok())
+        .filter(|c| last.map_or(true, |l| c > l))
+        .collect();
+    res.sort();
+    res
+}
+
+/// Every combination in `hand`, found by trying each subset of it: the
+/// slow way that `combinations` replaces, kept to test and benchmark it
+/// against. Passing is not included.
+#[doc(hidden)]
+pub fn subset_scan(hand: &[Card], rules: &RuleSet) -> Vec<Cards> {
+    let mut res = vec![];
+    for mask in 1u32..(1 << hand.len()) {
+        let cards = (0..hand.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .map(|i| hand[i])
+            .collect();
+        if let Ok(cards) = Cards::with_rules(cards, rules) {
+            res.push(cards);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(s: &str) -> Hand {
+        s.split_whitespace().map(|c| c.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn indices() {
+        for i in 0..52 {
+            assert_eq!(i, index(card_at(i)));
+        }
+        let h = hand("3C 2D AS");
+        assert_eq!(3, h.len());
+        assert!(h.contains("AS".parse().unwrap()));
+        assert_eq!("3C AS 2D", Cards(h.cards(), 0, None).to_string());
+    }
+
+    #[test]
+    fn all_combinations() {
+        let rules = RuleSet::default();
+        let h = hand("3C 3S 3H 4C 5C 6C 7C");
+        let combis = combinations(h, &rules, None);
+        // The only 5-card combinations are the three 3-7 straights, one of
+        // which is a straight flush.
+        let count = |n| combis.iter().filter(|c| c.len() == n).count();
+        assert_eq!(7, count(1));
+        assert_eq!(3, count(2));
+        assert_eq!(1, count(3));
+        assert_eq!(3, count(5));
+    }
+
+    #[test]
+    fn beating_last() {
+        let rules = RuleSet::default();
+        let h = hand("3C 3S 9H 9D KC KS");
+        let last: Cards = "9C 9S".parse().unwrap();
+        let combis = combinations(h, &rules, Some(&last));
+        let strs: Vec<_> = combis.iter().map(|c| c.to_string()).collect();
+        assert_eq!(vec!["9H 9D", "KC KS"], strs);
+    }
+}
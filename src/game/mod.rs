@@ -0,0 +1,1061 @@
+//! Rules engine for Big Two (a.k.a. Pusoy Dos).
+//!
+//! A `Game` deals a shuffled deck to two to four players and then accepts
+//! plays, in turn order, as `Cards`. The engine checks every play against
+//! the rules and keeps track of whose turn it is and who has emptied their
+//! hand.
+//!
+//! Deals can be reproduced with `Game::with_seed` or set up explicitly
+//! with `Game::from_hands`. House rules are configured with a `RuleSet`.
+//!
+//! Every game keeps a history of `Event`s from the deal onwards, from which
+//! it can be replayed up to any move or have moves undone. Games in
+//! progress can be saved as text, see `save`.
+
+use std::fmt;
+use std::error;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+use std::ops::Index;
+
+use rand::{self, Rng, SeedableRng, Isaac64Rng};
+
+pub use self::rules::{RuleSet, Deal, FlushOrder, Lead, RulesError};
+pub use self::score::{Match, MatchEnd};
+pub use self::hand::Hand;
+pub use self::save::LoadError;
+pub use self::view::PlayerView;
+
+pub mod rules;
+pub mod score;
+pub mod hand;
+pub mod record;
+pub mod save;
+pub mod solver;
+pub mod view;
+
+const RANKS: &str = "3456789TJQKA2";
+const SUITS: &str = "CSHD";
+
+/// A single playing card, e.g. `3C` for the three of clubs.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct Card {
+    pub rank: char,
+    pub suit: char,
+}
+
+type Value = usize;
+
+pub const MAX_PLAYERS: usize = 4;
+
+/// The card that has to be played on the very first turn under the default
+/// rules.
+pub const LOWEST_CARD: Card = Card{rank: '3', suit: 'C'};
+
+/// A play that breaks the rules.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameError {
+    PairMismatch,
+    TrioMismatch,
+    InvalidCombination,
+    InvalidLength,
+    NotInHand,
+    CannotPass,
+    MissingLowestCard,
+    LengthMismatch,
+    TooLow,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            GameError::PairMismatch => "pair doesn't match",
+            GameError::TrioMismatch => "trio doesn't match",
+            GameError::InvalidCombination => "invalid 5-card combination",
+            GameError::InvalidLength => "invalid length",
+            GameError::NotInHand => "some cards are not in player's hands",
+            GameError::CannotPass => "cannot pass",
+            GameError::MissingLowestCard =>
+                "first play must include the lowest card",
+            GameError::LengthMismatch =>
+                "should follow the number of cards of last play",
+            GameError::TooLow => "played cards are lower than last",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl error::Error for GameError {}
+
+/// Text that cannot be read as cards.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseError {
+    Length,
+    Rank(char),
+    Suit(char),
+    Duplicate(Card),
+    Combination(GameError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Length => write!(f, "must be of length 2"),
+            ParseError::Rank(r) => write!(f, "invalid rank {}", r),
+            ParseError::Suit(s) => write!(f, "invalid suit {}", s),
+            ParseError::Duplicate(_) => write!(f, "cards are not unique"),
+            ParseError::Combination(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Hands that cannot be dealt to a game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DealError {
+    Players(usize),
+    HandSize(PlayerNum),
+    Duplicate(Card),
+}
+
+impl fmt::Display for DealError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DealError::Players(n) => write!(f, "cannot deal to {} players", n),
+            DealError::HandSize(p) => {
+                write!(f, "#{} is dealt the wrong number of cards", p)
+            }
+            DealError::Duplicate(c) => write!(f, "{} is dealt twice", c),
+        }
+    }
+}
+
+impl error::Error for DealError {}
+
+impl From<GameError> for ParseError {
+    fn from(e: GameError) -> ParseError {
+        ParseError::Combination(e)
+    }
+}
+
+/// The type of a combination, along with its key card: the card that
+/// decides its rank among combinations of the same type (the highest card,
+/// or for a full house or four of a kind, a card of the trio or quad).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Kind {
+    Single(Card),
+    Pair(Card),
+    Trio(Card),
+    Straight(Card),
+    Flush(Card),
+    FullHouse(Card),
+    FourOfAKind(Card),
+    StraightFlush(Card),
+}
+
+impl Kind {
+    pub fn card(&self) -> Card {
+        match *self {
+            Kind::Single(c) | Kind::Pair(c) | Kind::Trio(c)
+                | Kind::Straight(c) | Kind::Flush(c) | Kind::FullHouse(c)
+                | Kind::FourOfAKind(c) | Kind::StraightFlush(c) => c,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Kind::Single(_) => "single",
+            Kind::Pair(_) => "pair",
+            Kind::Trio(_) => "trio",
+            Kind::Straight(_) => "straight",
+            Kind::Flush(_) => "flush",
+            Kind::FullHouse(_) => "full house",
+            Kind::FourOfAKind(_) => "four of a kind",
+            Kind::StraightFlush(_) => "straight flush",
+        }
+    }
+
+    /// How 5-card combinations of different kinds rank against each other.
+    fn order(&self) -> Value {
+        match *self {
+            Kind::Straight(_) => 1,
+            Kind::Flush(_) => 2,
+            Kind::FullHouse(_) => 3,
+            Kind::FourOfAKind(_) => 4,
+            Kind::StraightFlush(_) => 5,
+            _ => 0,
+        }
+    }
+}
+
+fn rank_name(rank: char) -> &'static str {
+    match rank {
+        '3' => "three",
+        '4' => "four",
+        '5' => "five",
+        '6' => "six",
+        '7' => "seven",
+        '8' => "eight",
+        '9' => "nine",
+        'T' => "ten",
+        'J' => "jack",
+        'Q' => "queen",
+        'K' => "king",
+        'A' => "ace",
+        '2' => "two",
+        _ => unreachable!(),
+    }
+}
+
+fn rank_plural(rank: char) -> String {
+    match rank {
+        '6' => "sixes".into(),
+        _ => format!("{}s", rank_name(rank)),
+    }
+}
+
+/// E.g. "a full house (kings)" or "a straight (ten high)".
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rank = self.card().rank;
+        match *self {
+            Kind::Single(c) => write!(f, "a single ({})", c),
+            Kind::Straight(_) | Kind::Flush(_) | Kind::StraightFlush(_) => {
+                write!(f, "a {} ({} high)", self.name(), rank_name(rank))
+            }
+            Kind::FourOfAKind(_) => {
+                write!(f, "{} ({})", self.name(), rank_plural(rank))
+            }
+            _ => write!(f, "a {} ({})", self.name(), rank_plural(rank)),
+        }
+    }
+}
+
+impl Card {
+    fn value(&self) -> Value {
+        RANKS.find(self.rank).unwrap()*4
+            + SUITS.find(self.suit).unwrap()
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.rank, self.suit)
+    }
+}
+
+impl FromStr for Card {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 2 {
+            Err(ParseError::Length)
+        } else {
+            let mut chars = s.chars();
+            let card = Card{
+                rank: chars.next().unwrap(),
+                suit: chars.next().unwrap(),
+            };
+            if RANKS.find(card.rank).is_none() {
+                Err(ParseError::Rank(card.rank))
+            } else if SUITS.find(card.suit).is_none() {
+                Err(ParseError::Suit(card.suit))
+            } else {
+                Ok(card)
+            }
+        }
+    }
+}
+
+impl Ord for Card {
+    fn cmp(&self, other: &Card) -> Ordering {
+        self.value().cmp(&other.value())
+    }
+}
+
+impl PartialOrd for Card {
+    fn partial_cmp(&self, other: &Card) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn new_deck(seed: u64) -> Vec<Card> {
+    let mut d = vec![];
+    for rank in RANKS.chars() {
+        for suit in SUITS.chars() {
+            d.push(Card{rank, suit});
+        }
+    }
+    let mut rng = Isaac64Rng::from_seed(&[seed]);
+    rng.shuffle(&mut d);
+    d
+}
+
+/// A valid combination of cards: a pass (no cards), a single, a pair, a
+/// trio or a 5-card combination.
+#[derive(Debug, Clone)]
+pub struct Cards(Vec<Card>, Value, Option<Kind>);
+
+impl fmt::Display for Cards {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let str_cards: Vec<_> = self.0.iter()
+            .map(|c| c.to_string())
+            .collect();
+        write!(f, "{}", str_cards.join(" "))
+    }
+}
+
+impl FromStr for Cards {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cards = vec![];
+        for c in s.split_whitespace() {
+            let card = Card::from_str(c)?;
+            if cards.contains(&card) {
+                return Err(ParseError::Duplicate(card))
+            }
+            cards.push(card);
+        }
+        Ok(Cards::new(cards)?)
+    }
+}
+
+impl IntoIterator for &Cards {
+    type Item = Card;
+    type IntoIter = ::std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.clone().into_iter()
+    }
+}
+
+impl Cards {
+    /// Creates a combination out of `cards` under the default rules,
+    /// failing if they do not form a valid one.
+    pub fn new(cards: Vec<Card>) -> Result<Cards, GameError> {
+        Cards::with_rules(cards, &RuleSet::default())
+    }
+
+    /// Creates a combination out of `cards` under `rules`. Only
+    /// combinations created under the same rules can be compared.
+    pub fn with_rules(cards: Vec<Card>, rules: &RuleSet)
+                      -> Result<Cards, GameError> {
+        let (val, kind) = Cards::value(cards.clone(), rules)?;
+        Ok(Cards(cards, val, kind))
+    }
+
+    /// The empty combination.
+    pub fn pass() -> Cards {
+        Cards(vec![], 0, None)
+    }
+
+    /// The type of combination, or `None` for a pass.
+    pub fn kind(&self) -> Option<Kind> {
+        self.2
+    }
+
+    pub fn is_pass(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        &self.0
+    }
+
+    pub fn contains(&self, card: &Card) -> bool {
+        self.0.contains(card)
+    }
+
+    /// Whether `self` can be played on top of `other`, i.e. both have the
+    /// same number of cards and `self` is higher.
+    pub fn beats(&self, other: &Cards) -> bool {
+        self.len() == other.len() && self > other
+    }
+
+    fn value(mut cards: Vec<Card>, rules: &RuleSet)
+             -> Result<(Value, Option<Kind>), GameError> {
+        rules.sort(&mut cards);
+        let cards = cards.as_slice();
+        let value = |card: Card| rules.card_value(card);
+
+        fn is_same_rank(cards: &[Card]) -> bool {
+            cards[0].rank == cards[cards.len()-1].rank
+        }
+
+        let straight = |cards: &[Card]| -> Option<(Value, Card)> {
+            fn num(card: Card) -> Value {
+                let r = RANKS.find(card.rank).unwrap();
+                (r + 2) % 13 // shift it so that A = 0, 2 = 1, etc.
+            }
+            let mut nums: Vec<Value> = cards.iter().map(|&c| num(c)).collect();
+            nums.sort();
+            nums.dedup();
+            if nums.len() != 5 {
+                return None
+            }
+            // Five distinct ranks form a run when exactly one gap between
+            // them (going around from K back to A) is not a step of one.
+            let start = (0..5).find(|&i| {
+                let prev = nums[(i + 4) % 5];
+                (prev + 1) % 13 != nums[i]
+            })?;
+            if nums[(start + 4) % 5] != (nums[start] + 4) % 13
+                || !rules.is_straight_start(nums[start]) {
+                return None
+            }
+            // Use the value of the card at the top of the run.
+            let top = nums[(start + 4) % 5];
+            let card = *cards.iter().find(|&&c| num(c) == top).unwrap();
+            Some((value(card), card))
+        };
+
+        let flush = |cards: &[Card]| -> Option<Value> {
+            if cards.iter().all(|c| c.suit == cards[0].suit) {
+                let rank = RANKS.find(cards[4].rank).unwrap();
+                Some(match rules.flush {
+                    FlushOrder::SuitFirst => {
+                        rules.suit_value(cards[4].suit)*13 + rank
+                    }
+                    FlushOrder::RankFirst => value(cards[4]),
+                })
+            } else {
+                None
+            }
+        };
+
+        let quadro = |cards: &[Card]| -> Option<Value> {
+            if is_same_rank(&cards[0..4]) || is_same_rank(&cards[1..5]) {
+                Some(value(cards[1]))
+            } else {
+                None
+            }
+        };
+
+        let full_house = |cards: &[Card]| -> Option<Value> {
+            if (is_same_rank(&cards[0..2]) && is_same_rank(&cards[2..5]))
+                || (is_same_rank(&cards[0..3]) && is_same_rank(&cards[3..5])) {
+                // The middle card always belongs to the trio.
+                Some(value(cards[2]))
+            } else {
+                None
+            }
+        };
+
+        match cards.len() {
+            0 => Ok((0, None)),
+            1 => Ok((value(cards[0]), Some(Kind::Single(cards[0])))),
+            2 => {
+                if is_same_rank(cards) {
+                    Ok((value(cards[1]), Some(Kind::Pair(cards[1]))))
+                } else {
+                    Err(GameError::PairMismatch)
+                }
+            }
+            3 => {
+                if is_same_rank(cards) {
+                    Ok((value(cards[2]), Some(Kind::Trio(cards[2]))))
+                } else {
+                    Err(GameError::TrioMismatch)
+                }
+            }
+            5 => {
+                let (kind, val) = if let Some((val, card)) = straight(cards) {
+                    if flush(cards).is_some() {
+                        (Kind::StraightFlush(card), val)
+                    } else {
+                        (Kind::Straight(card), val)
+                    }
+                } else if let Some(val) = quadro(cards) {
+                    (Kind::FourOfAKind(cards[1]), val)
+                } else if let Some(val) = full_house(cards) {
+                    (Kind::FullHouse(cards[2]), val)
+                } else if let Some(val) = flush(cards) {
+                    (Kind::Flush(cards[4]), val)
+                } else {
+                    return Err(GameError::InvalidCombination)
+                };
+                Ok((kind.order()*1000 + val, Some(kind)))
+            }
+            _ => {
+                Err(GameError::InvalidLength)
+            }
+        }
+    }
+}
+
+impl Ord for Cards {
+    fn cmp(&self, other: &Cards) -> Ordering {
+        let (len1, len2) = (self.len(), other.len());
+        if len1 == len2 {
+            self.1.cmp(&other.1)
+        } else {
+            // Comparing cards with different cardinalities doesn't
+            // really make sense, we just do it here for convenience
+            // (e.g. in sorting).
+            len2.cmp(&len1)
+        }
+    }
+}
+
+impl PartialOrd for Cards {
+    fn partial_cmp(&self, other: &Cards) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Cards {
+    fn eq(&self, other: &Cards) -> bool {
+        self.1 == other.1
+    }
+}
+impl Eq for Cards {}
+
+impl Index<usize> for Cards {
+    type Output = Card;
+    fn index(&self, index: usize) -> &Card {
+        self.0.index(index)
+    }
+}
+
+/// Players are numbered from 1.
+pub type PlayerNum = usize;
+
+/// Whose turn it is and what they are allowed to play.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Turn {
+    /// The very first play of the game, which has to include the lowest
+    /// card.
+    Start(PlayerNum),
+    /// The player has to beat the last play or pass.
+    Follow(PlayerNum),
+    /// The player has control and may play any combination.
+    Any(PlayerNum),
+    /// All but one player have emptied their hands.
+    End,
+}
+
+impl Turn {
+    /// Panics on `Turn::End`.
+    pub fn player(&self) -> PlayerNum {
+        match *self {
+            Turn::Start(p) | Turn::Follow(p) | Turn::Any(p) => p,
+            Turn::End => unreachable!(),
+        }
+    }
+}
+
+/// Something that happened in a game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The hands in seat order, and the turn of the first play.
+    Deal(Vec<Hand>, Turn),
+    Play(PlayerNum, Cards),
+    Pass(PlayerNum),
+    /// Everyone else has passed or gone out, and the player has control.
+    NewTrick(PlayerNum),
+    /// The player has emptied their hand.
+    Win(PlayerNum),
+}
+
+/// The state of a single game of Big Two.
+#[derive(Clone)]
+pub struct Game {
+    rules: RuleSet,
+    seed: Option<u64>,
+    lowest: Card,
+    lead_lowest: bool,
+    curr_player: PlayerNum,
+    hands: Vec<Hand>,
+    discard_pile: Vec<Card>,
+    last_play: Option<(PlayerNum, Cards)>,
+    winners: Vec<PlayerNum>,
+    history: Vec<Event>,
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new(RuleSet::default())
+    }
+}
+
+impl Game {
+    /// Deals a freshly shuffled deck to `rules.players` players.
+    pub fn new(rules: RuleSet) -> Game {
+        Game::with_seed(rules, rand::thread_rng().gen())
+    }
+
+    /// Deals a deck shuffled from `seed`. The same seed always gives the
+    /// same deal.
+    pub fn with_seed(rules: RuleSet, seed: u64) -> Game {
+        let mut deck = new_deck(seed);
+        let size = rules.hand_size();
+        let mut hands: Vec<Vec<_>> = (0..rules.players)
+            .map(|_| deck.drain(..size).collect())
+            .collect();
+        if rules.deal == Deal::Full && !deck.is_empty() {
+            let lowest = hands.iter().flat_map(|h| h.iter().cloned())
+                .min_by_key(|&c| rules.card_value(c))
+                .unwrap();
+            let hand = hands.iter_mut().find(|h| h.contains(&lowest)).unwrap();
+            hand.append(&mut deck);
+        }
+        let mut game = Game::from_hands(rules, hands).unwrap();
+        game.seed = Some(seed);
+        game
+    }
+
+    /// Starts a game with the given hands, one for each player. The number
+    /// of cards in each hand has to match `rules.deal`.
+    pub fn from_hands(rules: RuleSet, hands: Vec<Vec<Card>>)
+                      -> Result<Game, DealError> {
+        if hands.len() != rules.players {
+            return Err(DealError::Players(hands.len()))
+        }
+        let lowest = match hands.iter().flat_map(|h| h.iter().cloned())
+            .min_by_key(|&c| rules.card_value(c)) {
+                Some(c) => c,
+                None => return Err(DealError::HandSize(1)),
+            };
+        let size = rules.hand_size();
+        let leftover = match rules.deal {
+            Deal::Full => 52 - size*rules.players,
+            Deal::Thirteen => 0,
+        };
+        let mut game = Game {
+            rules,
+            seed: None,
+            lowest,
+            lead_lowest: false,
+            curr_player: 0,
+            hands: vec![],
+            discard_pile: vec![],
+            last_play: None,
+            winners: vec![],
+            history: vec![],
+        };
+        let mut dealt = BTreeSet::new();
+        for (i, cards) in hands.into_iter().enumerate() {
+            let p = i + 1;
+            let expected = if cards.contains(&lowest) {
+                size + leftover
+            } else {
+                size
+            };
+            if cards.len() != expected {
+                return Err(DealError::HandSize(p))
+            }
+            let mut hand = Hand::new();
+            for card in cards {
+                if !dealt.insert(card) {
+                    return Err(DealError::Duplicate(card))
+                }
+                hand.insert(card);
+            }
+            if hand.contains(lowest) {
+                game.curr_player = p;
+            }
+            game.hands.push(hand);
+        }
+        assert_ne!(0, game.curr_player);
+        if game.rules.lead == Lead::PlayLowest {
+            game.lead_lowest = true;
+        }
+        game.history.push(Event::Deal(game.hands.clone(), game.turn()));
+        Ok(game)
+    }
+
+    /// The seed the deck was shuffled with, if the deal was random.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// The lowest card that was dealt, which is usually the first card to
+    /// be played.
+    pub fn lowest_card(&self) -> Card {
+        self.lowest
+    }
+
+    /// Lets `p` make the first play, with no restriction on what they
+    /// play. Has no effect once the first play has been made.
+    pub fn set_leader(&mut self, p: PlayerNum) {
+        if self.last_play.is_none() {
+            self.curr_player = p;
+            self.lead_lowest = false;
+            self.history[0] = Event::Deal(self.hands.clone(), self.turn());
+        }
+    }
+
+    pub fn players(&self) -> usize {
+        self.hands.len()
+    }
+
+    pub fn turn(&self) -> Turn {
+        if self.winners.len() == self.players() - 1 {
+            Turn::End
+        } else if let Some((p, _)) = self.last_play {
+            if p == self.curr_player || self.hands[p-1].is_empty() {
+                Turn::Any(self.curr_player)
+            } else {
+                Turn::Follow(self.curr_player)
+            }
+        } else if self.lead_lowest {
+            Turn::Start(self.curr_player)
+        } else {
+            Turn::Any(self.curr_player)
+        }
+    }
+
+    /// The cards of player `p`, from lowest to highest.
+    pub fn hand(&self, p: PlayerNum) -> Vec<Card> {
+        let mut hand = self.hands[p-1].cards();
+        self.rules.sort(&mut hand);
+        hand
+    }
+
+    /// The number of cards each player holds, in seat order.
+    pub fn card_counts(&self) -> Vec<usize> {
+        self.hands.iter().map(|h| h.len()).collect()
+    }
+
+    /// The bitboard of player `p`'s cards.
+    pub fn hand_bits(&self, p: PlayerNum) -> Hand {
+        self.hands[p-1]
+    }
+
+    /// Everything `p` may play now, including passing when allowed. Empty
+    /// if it is not `p`'s turn.
+    pub fn legal_moves(&self, p: PlayerNum) -> Vec<Cards> {
+        let t = self.turn();
+        match t {
+            Turn::End => return vec![],
+            _ if t.player() != p => return vec![],
+            _ => (),
+        }
+        let hand = self.hands[p-1];
+        match t {
+            Turn::Start(_) => {
+                let mut moves = hand::combinations(hand, &self.rules, None);
+                moves.retain(|c| c.contains(&self.lowest));
+                moves
+            }
+            Turn::Follow(_) => {
+                let last = &self.last_play.as_ref().unwrap().1;
+                let mut moves =
+                    hand::combinations(hand, &self.rules, Some(last));
+                moves.push(Cards::pass());
+                moves
+            }
+            _ => hand::combinations(hand, &self.rules, None),
+        }
+    }
+
+    /// The last non-pass play and who made it.
+    pub fn last_play(&self) -> Option<&(PlayerNum, Cards)> {
+        self.last_play.as_ref()
+    }
+
+    /// Every card played so far, in the order they were played.
+    pub fn discard_pile(&self) -> &[Card] {
+        &self.discard_pile
+    }
+
+    /// Plays `cards` for the player whose turn it is. Returns whether the
+    /// player has emptied their hand with this play.
+    pub fn play(&mut self, cards: &Cards) -> Result<bool, GameError> {
+        let t = self.turn();
+        if !self.is_in_hand(t.player(), cards) {
+            return Err(GameError::NotInHand)
+        }
+        // The cards may have been valued under different rules.
+        let cards = &Cards::with_rules(cards.0.clone(), &self.rules)?;
+        match t {
+            Turn::Start(_) => {
+                if cards.is_pass() {
+                    return Err(GameError::CannotPass)
+                } else if !cards.0.contains(&self.lowest) {
+                    return Err(GameError::MissingLowestCard)
+                }
+            }
+            Turn::Follow(_) => {
+                if !cards.is_pass() {
+                    let last_cards = &self.last_play.as_ref().unwrap().1;
+                    if last_cards.0.len() != cards.0.len() {
+                        return Err(GameError::LengthMismatch)
+                    }
+                    if cards <= last_cards {
+                        return Err(GameError::TooLow)
+                    }
+                }
+            }
+            Turn::Any(_) => {
+                if cards.is_pass() {
+                    return Err(GameError::CannotPass)
+                }
+            }
+            Turn::End => unreachable!(),
+        }
+        for card in &cards.0 {
+            self.hands[t.player()-1].remove(*card);
+            self.discard_pile.push(*card);
+        }
+        if cards.is_pass() {
+            self.history.push(Event::Pass(t.player()));
+        } else {
+            self.last_play = Some((t.player(), cards.clone()));
+            self.history.push(Event::Play(t.player(), cards.clone()));
+        }
+        let mut wins = false;
+        if self.hands[t.player()-1].is_empty() {
+            self.winners.push(t.player());
+            self.history.push(Event::Win(t.player()));
+            wins = true;
+        }
+        self.inc_turn();
+        if let Turn::Any(p) = self.turn() {
+            self.history.push(Event::NewTrick(p));
+        }
+        Ok(wins)
+    }
+
+    /// Everything that has happened so far, starting with the deal.
+    pub fn history(&self) -> &[Event] {
+        &self.history
+    }
+
+    /// The plays and passes made so far, in order, with passes as
+    /// `Cards::pass()`.
+    pub fn moves(&self) -> Vec<(PlayerNum, Cards)> {
+        self.history.iter().filter_map(|e| match *e {
+            Event::Play(p, ref cards) => Some((p, cards.clone())),
+            Event::Pass(p) => Some((p, Cards::pass())),
+            _ => None,
+        }).collect()
+    }
+
+    /// The game as it was after its first `moves` plays and passes,
+    /// replayed from the deal.
+    pub fn replay(&self, moves: usize) -> Game {
+        let (hands, turn) = match self.history[0] {
+            Event::Deal(ref hands, turn) => (hands, turn),
+            _ => unreachable!(),
+        };
+        let hands = hands.iter().map(|h| h.cards()).collect();
+        let mut game = Game::from_hands(self.rules.clone(), hands).unwrap();
+        game.seed = self.seed;
+        if let Turn::Any(p) = turn {
+            game.set_leader(p);
+        }
+        for (_, cards) in self.moves().into_iter().take(moves) {
+            game.play(&cards).unwrap();
+        }
+        game
+    }
+
+    /// What player `p` knows of the game: their own hand and everything
+    /// that has been played.
+    pub fn view(&self, p: PlayerNum) -> PlayerView {
+        let mut view = PlayerView::new(p, self.rules.clone(), vec![]);
+        for event in &self.history {
+            match *event {
+                Event::Deal(ref hands, turn) => {
+                    view.hand = hands[p-1].cards();
+                    self.rules.sort(&mut view.hand);
+                    view.card_counts = hands.iter().map(|h| h.len()).collect();
+                    view.set_turn(turn);
+                }
+                Event::Play(q, ref cards) => {
+                    view.add_play(q, cards.clone())
+                        .expect("plays in the history are legal");
+                }
+                Event::Pass(q) => {
+                    view.add_play(q, Cards::pass()).unwrap();
+                }
+                Event::NewTrick(q) => view.set_turn(Turn::Any(q)),
+                Event::Win(q) => view.add_win(q),
+            }
+        }
+        view.turn = Some(self.turn());
+        view
+    }
+
+    /// Takes back the last play or pass. Returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        let moves = self.moves().len();
+        if moves == 0 {
+            return false
+        }
+        *self = self.replay(moves - 1);
+        true
+    }
+
+    /// Players who have emptied their hands, from first to last.
+    pub fn winners(&self) -> Vec<PlayerNum> {
+        self.winners.clone()
+    }
+
+    fn is_in_hand(&self, p: PlayerNum, cards: &Cards) -> bool {
+        self.hands[p-1].is_superset(Hand::from(cards))
+    }
+
+    fn inc_turn(&mut self) {
+        if self.winners.len() == self.players() - 1 {
+            return
+        }
+        loop {
+            self.curr_player = (self.curr_player)%self.players() + 1;
+            if !self.hands[self.curr_player-1].is_empty() {
+                break
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gt(c1: Cards, c2: Cards) -> bool {
+        c1 > c2
+    }
+
+    fn with_rules(cards: &str, rules: &str) -> Result<Cards, GameError> {
+        let cards = cards.split_whitespace()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        Cards::with_rules(cards, &rules.parse().unwrap())
+    }
+
+    #[test]
+    fn straight_flush() {
+        // The high card in these cases are not the ace or two.
+        let c1 = "AD 2D 3D 4D 5D".parse().unwrap();
+        let c2 = "2D 3D 4D 5D 6D".parse().unwrap();
+        assert!(gt(c2, c1));
+        let c1 = "2D 3D 4D 5D 6D".parse().unwrap();
+        let c2 = "3D 4D 5D 6D 7D".parse().unwrap();
+        assert!(gt(c2, c1));
+
+        let c1 = "9D TD JD QD KD".parse().unwrap();
+        // This time the high card is the Ace.
+        let c2 = "AD KD QD JD TD".parse().unwrap();
+        assert!(gt(c2, c1));
+    }
+
+    #[test]
+    fn straight() {
+        // The high card in these cases are not the ace or two.
+        let c1 = "AC 2D 3D 4D 5D".parse().unwrap();
+        let c2 = "2C 3D 4D 5D 6D".parse().unwrap();
+        assert!(gt(c2, c1));
+        let c1 = "2C 3D 4D 5D 6D".parse().unwrap();
+        let c2 = "3C 4D 5D 6D 7D".parse().unwrap();
+        assert!(gt(c2, c1));
+
+        let c1 = "9C TD JD QD KD".parse().unwrap();
+        // This time the high card is the Ace.
+        let c2 = "AC KD QD JD TD".parse().unwrap();
+        assert!(gt(c2, c1));
+
+        // High card rank tied, break by suit.
+        let c1 = "AC KD QD JD TD".parse().unwrap();
+        let c2 = "AS KH QH JH TH".parse().unwrap();
+        assert!(gt(c2, c1));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(Err(ParseError::Length), "3".parse::<Card>());
+        assert_eq!(Err(ParseError::Rank('1')), "1C".parse::<Card>());
+        assert_eq!(Err(ParseError::Suit('X')), "3X".parse::<Card>());
+        let three = Card{rank: '3', suit: 'S'};
+        assert_eq!(Err(ParseError::Duplicate(three)),
+                   "3S 3S".parse::<Cards>().map(|_| ()));
+        assert_eq!(Err(ParseError::Combination(GameError::PairMismatch)),
+                   "3S 4S".parse::<Cards>().map(|_| ()));
+        assert_eq!(Err(ParseError::Combination(GameError::InvalidLength)),
+                   "3S 3H 3D 3C".parse::<Cards>().map(|_| ()));
+    }
+
+    #[test]
+    fn flush() {
+        // Suit is more important than high card.
+        let c1 = "AC 2C 3C 4C 6C".parse().unwrap();
+        let c2 = "9D KD 8D JD TD".parse().unwrap();
+        assert!(gt(c2, c1));
+
+        // If suit is tied, then high card is used as breaker.
+        let c1 = "AD KD 8D JD TD".parse().unwrap();
+        let c2 = "9D 2D 3D 4D 6D".parse().unwrap();
+        assert!(gt(c2, c1));
+    }
+
+    #[test]
+    fn suit_order() {
+        let c1 = with_rules("3D", "suits=DCHS").unwrap();
+        let c2 = with_rules("3C", "suits=DCHS").unwrap();
+        assert!(gt(c2, c1));
+        let c1 = with_rules("KC KD", "suits=DCHS").unwrap();
+        let c2 = with_rules("KS KH", "suits=DCHS").unwrap();
+        assert!(gt(c2, c1));
+    }
+
+    #[test]
+    fn straight_rules() {
+        assert_eq!(Err(GameError::InvalidCombination),
+                   with_rules("AC 2D 3D 4D 5D", "ace-low=no").map(|_| ()));
+        assert!(with_rules("2C 3D 4D 5D 6D", "ace-low=no").is_ok());
+        assert_eq!(Err(GameError::InvalidCombination),
+                   with_rules("2C 3D 4D 5D 6D", "twos=no").map(|_| ()));
+        assert_eq!(Err(GameError::InvalidCombination),
+                   with_rules("JC QD KD AD 2D", "").map(|_| ()));
+        let c1 = with_rules("TC JD QD KD AD", "wraparound=yes").unwrap();
+        let c2 = with_rules("JC QD KD AD 2D", "wraparound=yes").unwrap();
+        assert!(gt(c2, c1));
+        assert!(with_rules("KC AD 2D 3D 4D", "wraparound=yes").is_ok());
+        assert_eq!(Err(GameError::InvalidCombination),
+                   with_rules("QC KD AD 2D 4D", "wraparound=yes").map(|_| ()));
+    }
+
+    #[test]
+    fn flush_rank_first() {
+        // The higher card wins regardless of suit.
+        let c1 = with_rules("AC 2C 3C 4C 6C", "flush=rank").unwrap();
+        let c2 = with_rules("9D KD 8D JD TD", "flush=rank").unwrap();
+        assert!(gt(c1, c2));
+    }
+
+    #[test]
+    fn kinds() {
+        let kind = |s: &str| s.parse::<Cards>().unwrap().kind();
+        let card = |s: &str| s.parse::<Card>().unwrap();
+        assert_eq!(None, kind(""));
+        assert_eq!(Some(Kind::Single(card("KS"))), kind("KS"));
+        assert_eq!(Some(Kind::Pair(card("KD"))), kind("KD KC"));
+        assert_eq!(Some(Kind::FullHouse(card("KC"))), kind("KC 3D KD 3S KS"));
+        assert_eq!(Some(Kind::FourOfAKind(card("6C"))),
+                   kind("6C 6S 6H 6D 3C"));
+        assert_eq!(Some(Kind::Straight(card("5H"))), kind("AC 2D 3S 4S 5H"));
+        assert_eq!(Some(Kind::StraightFlush(card("7C"))),
+                   kind("3C 4C 5C 6C 7C"));
+        let house = kind("KC 3D KD 3S KS").unwrap();
+        assert_eq!("a full house (kings)", house.to_string());
+        let quad = kind("6C 6S 6H 6D 3C").unwrap();
+        assert_eq!("four of a kind (sixes)", quad.to_string());
+        let straight = kind("TC JD QS KS 9H").unwrap();
+        assert_eq!("a straight (king high)", straight.to_string());
+    }
+}
@@ -0,0 +1,262 @@
+//! Records of finished games, in the spirit of PGN for chess.
+//!
+//! A record starts with tags in brackets and then lists every move, one
+//! per line:
+//!
+//! ```text
+//! [Date "2024-05-01"]
+//! [Player "#1 127.0.0.1:50112"]
+//! [Player "#2 127.0.0.1:50114"]
+//! [Player "#3 127.0.0.1:50116"]
+//! [Seed "42"]
+//! [Rules "players=3 deal=full suits=CSHD ..."]
+//! [Result "#2 #3"]
+//! [Hand "#1 3C 5S .."]
+//! [Hand "#2 3D 4H .."]
+//! [Hand "#3 3S 4C .."]
+//! [Lead "#1 S"]
+//!
+//! 1. #1 3C 4D 5H 6S 7C
+//! 2. #2 pass
+//! 3. #3 9S TD JH QH KC
+//! ```
+//!
+//! There is a `Player` tag with who sat in each seat, and a `Hand` tag
+//! with the cards each player was dealt. `Result` lists the players in the
+//! order they went out. `Lead` gives the first turn like in saved games.
+//! `Seed` is left out if the deal wasn't random. Quotes, backslashes and
+//! line breaks in tag values are escaped with a backslash.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{Event, Game, LoadError, RuleSet, Turn};
+use super::save::{parse_hand, parse_lead, parse_player, replay_move};
+
+#[derive(Clone)]
+pub struct Record {
+    /// When the game was played, as `YYYY-MM-DD`.
+    pub date: String,
+    /// Who sat in each seat, in seat order.
+    pub players: Vec<String>,
+    pub game: Game,
+}
+
+impl Record {
+    /// A record of `game` dated today.
+    pub fn new(game: Game, players: Vec<String>) -> Record {
+        Record {
+            date: today(),
+            players,
+            game,
+        }
+    }
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+fn today() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    date((secs / 86400) as i64)
+}
+
+/// The date `days` after 1970-01-01, as `YYYY-MM-DD`.
+fn date(days: i64) -> String {
+    // Years are counted from March so that leap days come last.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe/1460 + doe/36524 - doe/146096) / 365;
+    let doy = doe - (365*yoe + yoe/4 - yoe/100);
+    let mp = (5*doy + 2) / 153;
+    let day = doy - (153*mp + 2)/5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Writes `[Key "value"]`, with backslashes, quotes and line breaks in the
+/// value escaped.
+fn tag(f: &mut fmt::Formatter, key: &str, value: &str) -> fmt::Result {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"")
+        .replace('\n', "\\n").replace('\r', "\\r");
+    writeln!(f, "[{} \"{}\"]", key, value)
+}
+
+fn join<T: ToString>(items: &[T]) -> String {
+    let items: Vec<_> = items.iter().map(|i| i.to_string()).collect();
+    items.join(" ")
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let game = &self.game;
+        tag(f, "Date", &self.date)?;
+        for (i, name) in self.players.iter().enumerate() {
+            tag(f, "Player", &format!("#{} {}", i+1, name))?;
+        }
+        if let Some(seed) = game.seed() {
+            tag(f, "Seed", &seed.to_string())?;
+        }
+        tag(f, "Rules", &game.rules().to_string())?;
+        let winners: Vec<_> = game.winners().iter()
+            .map(|w| format!("#{}", w))
+            .collect();
+        tag(f, "Result", &winners.join(" "))?;
+        let mut n = 0;
+        for event in game.history() {
+            match *event {
+                Event::Deal(ref hands, turn) => {
+                    for (i, hand) in hands.iter().enumerate() {
+                        let mut cards = hand.cards();
+                        game.rules().sort(&mut cards);
+                        tag(f, "Hand", &format!("#{} {}", i+1, join(&cards)))?;
+                    }
+                    let lead = match turn {
+                        Turn::Start(p) => format!("#{} S", p),
+                        t => format!("#{} A", t.player()),
+                    };
+                    tag(f, "Lead", &lead)?;
+                    writeln!(f)?;
+                }
+                Event::Play(p, ref cards) => {
+                    n += 1;
+                    writeln!(f, "{}. #{} {}", n, p, join(cards.cards()))?;
+                }
+                Event::Pass(p) => {
+                    n += 1;
+                    writeln!(f, "{}. #{} pass", n, p)?;
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads `[Key "value"]`, undoing the escapes of `tag`.
+fn parse_tag(line: &str) -> Option<(&str, String)> {
+    if !line.starts_with('[') || !line.ends_with("\"]") {
+        return None
+    }
+    let mut parts = line[1..line.len()-2].splitn(2, " \"");
+    let key = parts.next()?;
+    let mut value = String::new();
+    let mut chars = parts.next()?.chars();
+    while let Some(c) = chars.next() {
+        value.push(match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                'r' => '\r',
+                c => c,
+            },
+            c => c,
+        });
+    }
+    Some((key, value))
+}
+
+impl FromStr for Record {
+    type Err = LoadError;
+
+    /// Reads a record and replays its moves.
+    fn from_str(s: &str) -> Result<Record, LoadError> {
+        let mut date = String::new();
+        let mut players = vec![];
+        let mut seed = None;
+        let mut rules = RuleSet::default();
+        let mut result = vec![];
+        let mut hands = vec![];
+        let mut game: Option<Game> = None;
+        let mut moves = 0;
+        for (i, line) in s.lines().enumerate() {
+            let invalid = LoadError::Line(i+1);
+            let line = line.trim();
+            if line.is_empty() {
+                continue
+            }
+            if let Some((key, value)) = parse_tag(line) {
+                let tokens: Vec<_> = value.split_whitespace().collect();
+                match key {
+                    "Date" => date = value.clone(),
+                    "Player" => {
+                        let mut parts = value.splitn(2, ' ');
+                        let p = parts.next().and_then(parse_player);
+                        if p != Some(players.len() + 1) {
+                            return Err(invalid)
+                        }
+                        players.push(parts.next().unwrap_or("").into());
+                    }
+                    "Seed" => seed = Some(value.parse().map_err(|_| invalid)?),
+                    "Rules" => rules = value.parse()?,
+                    "Result" => {
+                        result = tokens.iter().map(|t| parse_player(t))
+                            .collect::<Option<_>>()
+                            .ok_or(invalid)?;
+                    }
+                    "Hand" => parse_hand(&tokens, &mut hands, i+1)?,
+                    "Lead" => {
+                        game = Some(parse_lead(&rules, seed, &hands, &tokens,
+                                               i+1)?);
+                    }
+                    // Tags this version doesn't know about.
+                    _ => (),
+                }
+                continue
+            }
+            let g = game.as_mut().ok_or(invalid.clone())?;
+            let mut tokens: Vec<_> = line.split_whitespace().collect();
+            moves += 1;
+            if tokens.len() < 2 || tokens[0] != format!("{}.", moves) {
+                return Err(invalid)
+            }
+            if tokens[2..] == ["pass"] {
+                tokens.truncate(2);
+            }
+            replay_move(g, moves, &tokens[1..], i+1)?;
+        }
+        let game = game.ok_or(LoadError::Line(s.lines().count() + 1))?;
+        if result != game.winners() {
+            return Err(LoadError::Mismatch("result"))
+        }
+        Ok(Record { date, players, game })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let rules: RuleSet = "players=3 lead=hold-lowest".parse().unwrap();
+        let mut game = Game::with_seed(rules, 9);
+        while game.turn() != Turn::End {
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            game.play(&moves[moves.len() / 2]).unwrap();
+        }
+        let players = vec!["ann, bo".into(), "bo \"b\"\\".into(),
+                           "#3\n".into()];
+        let record = Record::new(game, players.clone());
+        let text = record.to_string();
+        assert!(text.starts_with(&format!("[Date \"{}\"]\n", record.date)));
+        assert!(text.contains("\n1. #"));
+        let parsed: Record = text.parse().unwrap();
+        assert_eq!(players, parsed.players);
+        assert_eq!(record.game.history(), parsed.game.history());
+        assert_eq!(text, parsed.to_string());
+        // Moves that lead somewhere else than the result.
+        let text = text.replace("[Result \"", "[Result \"#9 ");
+        assert!(text.parse::<Record>().is_err());
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!("1970-01-01", date(0));
+        assert_eq!("2024-02-29", date(19782));
+        assert_eq!("2024-03-01", date(19783));
+    }
+}
@@ -0,0 +1,251 @@
+//! House rules that differ between groups of Big Two players.
+//!
+//! A `RuleSet` is written as space-separated `key=value` pairs, e.g.
+//! `suits=DCHS flush=rank ace-low=no`. Keys that are left out keep their
+//! default value.
+
+use std::fmt;
+use std::error;
+use std::str::FromStr;
+
+use super::{Card, RANKS, SUITS, MAX_PLAYERS};
+
+/// How two flushes are compared.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FlushOrder {
+    /// By suit, then by the highest card.
+    SuitFirst,
+    /// By the highest card only.
+    RankFirst,
+}
+
+/// How the deck is dealt when there are fewer than four players.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Deal {
+    /// Deal the whole deck evenly. Any card left over goes to whoever holds
+    /// the lowest card.
+    Full,
+    /// Deal 13 cards to each player and leave the rest undealt.
+    Thirteen,
+}
+
+/// Who makes the first play of the game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Lead {
+    /// The holder of the lowest card, who has to play it.
+    PlayLowest,
+    /// The holder of the lowest card, who may play anything.
+    HoldLowest,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RuleSet {
+    /// From 2 to 4.
+    pub players: usize,
+    pub deal: Deal,
+    /// Suits from lowest to highest.
+    pub suits: [char; 4],
+    pub flush: FlushOrder,
+    /// Whether A-2-3-4-5 is a straight.
+    pub ace_low_straight: bool,
+    /// Whether J-Q-K-A-2, Q-K-A-2-3 and K-A-2-3-4 are straights.
+    pub wraparound_straights: bool,
+    /// Whether any straight may contain a 2 at all.
+    pub twos_in_straights: bool,
+    pub lead: Lead,
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet {
+            players: MAX_PLAYERS,
+            deal: Deal::Full,
+            suits: ['C', 'S', 'H', 'D'],
+            flush: FlushOrder::SuitFirst,
+            ace_low_straight: true,
+            wraparound_straights: false,
+            twos_in_straights: true,
+            lead: Lead::PlayLowest,
+        }
+    }
+}
+
+impl RuleSet {
+    /// The number of cards each player is dealt, not counting the cards
+    /// left over from a full deal.
+    pub fn hand_size(&self) -> usize {
+        match self.deal {
+            Deal::Full => 52 / self.players,
+            Deal::Thirteen => 13,
+        }
+    }
+
+    /// The three of the lowest suit.
+    pub fn lowest_card(&self) -> Card {
+        Card{rank: '3', suit: self.suits[0]}
+    }
+
+    pub fn suit_value(&self, suit: char) -> usize {
+        self.suits.iter().position(|&s| s == suit).unwrap()
+    }
+
+    pub fn card_value(&self, card: Card) -> usize {
+        RANKS.find(card.rank).unwrap()*4 + self.suit_value(card.suit)
+    }
+
+    /// Sorts `cards` from lowest to highest under these rules.
+    pub fn sort(&self, cards: &mut [Card]) {
+        cards.sort_by_key(|&c| self.card_value(c));
+    }
+
+    /// Whether five consecutive ranks starting from `start` form a
+    /// straight, where `start` counts from the ace (A = 0, 2 = 1, etc.).
+    pub fn is_straight_start(&self, start: usize) -> bool {
+        match start {
+            0 => self.ace_low_straight && self.twos_in_straights,
+            1 => self.twos_in_straights,
+            2..=9 => true,
+            10..=12 => self.wraparound_straights && self.twos_in_straights,
+            _ => false,
+        }
+    }
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b { "yes" } else { "no" }
+}
+
+impl fmt::Display for RuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let suits: String = self.suits.iter().collect();
+        write!(f, "players={} deal={} suits={} flush={} ace-low={} \
+                   wraparound={} twos={} lead={}",
+               self.players,
+               match self.deal {
+                   Deal::Full => "full",
+                   Deal::Thirteen => "13",
+               },
+               suits,
+               match self.flush {
+                   FlushOrder::SuitFirst => "suit",
+                   FlushOrder::RankFirst => "rank",
+               },
+               yes_no(self.ace_low_straight),
+               yes_no(self.wraparound_straights),
+               yes_no(self.twos_in_straights),
+               match self.lead {
+                   Lead::PlayLowest => "play-lowest",
+                   Lead::HoldLowest => "hold-lowest",
+               })
+    }
+}
+
+/// Text that cannot be read as a rule set.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum RulesError {
+    Key(String),
+    Value(String),
+}
+
+impl fmt::Display for RulesError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RulesError::Key(ref k) => write!(f, "unknown rule {}", k),
+            RulesError::Value(ref k) => write!(f, "invalid value for {}", k),
+        }
+    }
+}
+
+impl error::Error for RulesError {}
+
+impl FromStr for RuleSet {
+    type Err = RulesError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rules = RuleSet::default();
+        for token in s.split_whitespace() {
+            let kv: Vec<_> = token.splitn(2, '=').collect();
+            if kv.len() != 2 {
+                return Err(RulesError::Key(token.into()))
+            }
+            let (key, value) = (kv[0], kv[1]);
+            let invalid = || RulesError::Value(key.into());
+            let flag = || match value {
+                "yes" => Ok(true),
+                "no" => Ok(false),
+                _ => Err(invalid()),
+            };
+            match key {
+                "players" => {
+                    rules.players = match value.parse() {
+                        Ok(n) if (2..=MAX_PLAYERS).contains(&n) => n,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "deal" => {
+                    rules.deal = match value {
+                        "full" => Deal::Full,
+                        "13" => Deal::Thirteen,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "suits" => {
+                    let suits: Vec<_> = value.chars().collect();
+                    if suits.len() != 4
+                        || SUITS.chars().any(|s| !suits.contains(&s)) {
+                        return Err(invalid())
+                    }
+                    rules.suits.copy_from_slice(&suits);
+                }
+                "flush" => {
+                    rules.flush = match value {
+                        "suit" => FlushOrder::SuitFirst,
+                        "rank" => FlushOrder::RankFirst,
+                        _ => return Err(invalid()),
+                    };
+                }
+                "ace-low" => rules.ace_low_straight = flag()?,
+                "wraparound" => rules.wraparound_straights = flag()?,
+                "twos" => rules.twos_in_straights = flag()?,
+                "lead" => {
+                    rules.lead = match value {
+                        "play-lowest" => Lead::PlayLowest,
+                        "hold-lowest" => Lead::HoldLowest,
+                        _ => return Err(invalid()),
+                    };
+                }
+                _ => return Err(RulesError::Key(key.into())),
+            }
+        }
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let rules: RuleSet = "players=3 deal=13 suits=DCHS flush=rank \
+                              wraparound=yes".parse().unwrap();
+        assert_eq!(3, rules.players);
+        assert_eq!(Deal::Thirteen, rules.deal);
+        assert_eq!(['D', 'C', 'H', 'S'], rules.suits);
+        assert_eq!(FlushOrder::RankFirst, rules.flush);
+        assert!(rules.wraparound_straights);
+        assert_eq!(rules, rules.to_string().parse().unwrap());
+        assert_eq!(RuleSet::default(), "".parse().unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(Err(RulesError::Key("jokers".into())),
+                   "jokers=yes".parse::<RuleSet>());
+        assert_eq!(Err(RulesError::Value("suits".into())),
+                   "suits=CCHD".parse::<RuleSet>());
+        assert_eq!(Err(RulesError::Value("players".into())),
+                   "players=5".parse::<RuleSet>());
+        assert_eq!(Err(RulesError::Value("twos".into())),
+                   "twos=maybe".parse::<RuleSet>());
+    }
+}
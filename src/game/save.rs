@@ -0,0 +1,297 @@
+//! Saving games in progress as text and loading them back.
+//!
+//! A saved game is a list of lines, each starting with a key:
+//!
+//! ```text
+//! rules players=4 deal=full suits=CSHD ...
+//! seed 42
+//! hand #1 3C 5S ..
+//! lead #1 S
+//! play #1 3C 4D 5H 6S 7C
+//! pass #2
+//! turn #3 F
+//! last #1 3C 4D 5H 6S 7C
+//! winners #4
+//! ```
+//!
+//! There is a `hand` line for every player with the cards they were dealt,
+//! and `lead` gives the first turn (`S` to start, `A` to any). The `play`
+//! and `pass` lines are replayed in order. `turn`, `last` and `winners`
+//! describe where the game should end up and are checked against the
+//! replay; `seed`, `last` and `winners` are left out when there are none.
+
+use std::fmt;
+use std::error;
+
+use super::{Card, Cards, DealError, Event, Game, GameError, ParseError,
+            PlayerNum, RuleSet, RulesError, Turn, MAX_PLAYERS};
+
+/// Text that cannot be loaded as a game.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LoadError {
+    /// The line with this number (from 1) cannot be read.
+    Line(usize),
+    Rules(RulesError),
+    Cards(ParseError),
+    Deal(DealError),
+    /// The move with this number (from 1) breaks the rules.
+    Play(usize, GameError),
+    /// The saved state disagrees with the replayed game.
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Line(n) => write!(f, "invalid line {}", n),
+            LoadError::Rules(ref e) => write!(f, "{}", e),
+            LoadError::Cards(e) => write!(f, "{}", e),
+            LoadError::Deal(e) => write!(f, "{}", e),
+            LoadError::Play(n, e) => write!(f, "move {}: {}", n, e),
+            LoadError::Mismatch(what) => {
+                write!(f, "{} does not match the moves", what)
+            }
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl From<RulesError> for LoadError {
+    fn from(e: RulesError) -> LoadError {
+        LoadError::Rules(e)
+    }
+}
+
+impl From<ParseError> for LoadError {
+    fn from(e: ParseError) -> LoadError {
+        LoadError::Cards(e)
+    }
+}
+
+impl From<DealError> for LoadError {
+    fn from(e: DealError) -> LoadError {
+        LoadError::Deal(e)
+    }
+}
+
+fn join(cards: &[Card]) -> String {
+    let cards: Vec<_> = cards.iter().map(|c| c.to_string()).collect();
+    cards.join(" ")
+}
+
+fn turn_str(turn: Turn) -> String {
+    match turn {
+        Turn::Start(p) => format!("#{} S", p),
+        Turn::Follow(p) => format!("#{} F", p),
+        Turn::Any(p) => format!("#{} A", p),
+        Turn::End => "end".into(),
+    }
+}
+
+/// Reads `#N`.
+pub fn parse_player(s: &str) -> Option<PlayerNum> {
+    if !s.starts_with('#') {
+        return None
+    }
+    match s[1..].parse() {
+        Ok(p) if (1..=MAX_PLAYERS).contains(&p) => Some(p),
+        _ => None,
+    }
+}
+
+/// Reads `cards` as a combination under `rules`.
+pub fn parse_cards(cards: &[&str], rules: &RuleSet)
+                   -> Result<Cards, ParseError> {
+    let mut res = vec![];
+    for s in cards {
+        let card: Card = s.parse()?;
+        if res.contains(&card) {
+            return Err(ParseError::Duplicate(card))
+        }
+        res.push(card);
+    }
+    Ok(Cards::with_rules(res, rules)?)
+}
+
+/// Adds the hand given as `#N` and its cards to `hands`, where it has to
+/// be player N's. Errors name `line` if not.
+pub fn parse_hand(tokens: &[&str], hands: &mut Vec<Vec<Card>>, line: usize)
+                  -> Result<(), LoadError> {
+    if tokens.first().and_then(|t| parse_player(t)) != Some(hands.len() + 1) {
+        return Err(LoadError::Line(line))
+    }
+    let mut cards = vec![];
+    for t in &tokens[1..] {
+        cards.push(t.parse()?);
+    }
+    hands.push(cards);
+    Ok(())
+}
+
+/// Deals `hands` and gives the first turn as read from `#N S`, for the
+/// holder of the lowest card to start, or `#N A`.
+pub fn parse_lead(rules: &RuleSet, seed: Option<u64>, hands: &[Vec<Card>],
+                  tokens: &[&str], line: usize) -> Result<Game, LoadError> {
+    let mut game = Game::from_hands(rules.clone(), hands.to_vec())?;
+    game.seed = seed;
+    let player = tokens.first().and_then(|t| parse_player(t));
+    match (player, tokens.get(1)) {
+        (Some(p), Some(&"S")) if p == game.curr_player && game.lead_lowest => (),
+        (Some(p), Some(&"A")) if p <= game.players() => game.set_leader(p),
+        _ => return Err(LoadError::Line(line)),
+    }
+    Ok(game)
+}
+
+/// Replays move `number`, given as `#N` and the cards played, or no cards
+/// for a pass.
+pub fn replay_move(game: &mut Game, number: usize, tokens: &[&str],
+                   line: usize) -> Result<(), LoadError> {
+    let p = tokens.first().and_then(|t| parse_player(t))
+        .ok_or(LoadError::Line(line))?;
+    if game.turn() == Turn::End || game.turn().player() != p {
+        return Err(LoadError::Mismatch("player"))
+    }
+    let cards = parse_cards(&tokens[1..], &game.rules)?;
+    game.play(&cards).map_err(|e| LoadError::Play(number, e))?;
+    Ok(())
+}
+
+impl Game {
+    /// Writes the game as text that `Game::load` reads back.
+    pub fn save(&self) -> String {
+        let mut lines = vec![format!("rules {}", self.rules)];
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed {}", seed));
+        }
+        for event in &self.history {
+            match *event {
+                Event::Deal(ref hands, turn) => {
+                    for (i, hand) in hands.iter().enumerate() {
+                        let mut cards = hand.cards();
+                        self.rules.sort(&mut cards);
+                        lines.push(format!("hand #{} {}", i+1, join(&cards)));
+                    }
+                    lines.push(format!("lead {}", turn_str(turn)));
+                }
+                Event::Play(p, ref cards) => {
+                    lines.push(format!("play #{} {}", p, join(cards.cards())));
+                }
+                Event::Pass(p) => lines.push(format!("pass #{}", p)),
+                _ => (),
+            }
+        }
+        lines.push(format!("turn {}", turn_str(self.turn())));
+        if let Some((p, ref cards)) = self.last_play {
+            lines.push(format!("last #{} {}", p, join(cards.cards())));
+        }
+        if !self.winners.is_empty() {
+            let winners: Vec<_> = self.winners.iter()
+                .map(|w| format!("#{}", w))
+                .collect();
+            lines.push(format!("winners {}", winners.join(" ")));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Reads a game written by `Game::save` and replays its moves.
+    pub fn load(s: &str) -> Result<Game, LoadError> {
+        let mut rules = RuleSet::default();
+        let mut seed = None;
+        let mut hands = vec![];
+        let mut game: Option<Game> = None;
+        let mut moves = 0;
+        for (i, line) in s.lines().enumerate() {
+            let invalid = LoadError::Line(i+1);
+            let tokens: Vec<_> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue
+            }
+            let player = || tokens.get(1).and_then(|t| parse_player(t));
+            match (tokens[0], game.as_mut()) {
+                ("rules", None) => rules = tokens[1..].join(" ").parse()?,
+                ("seed", None) => {
+                    seed = match tokens.get(1).map(|t| t.parse()) {
+                        Some(Ok(n)) => Some(n),
+                        _ => return Err(invalid),
+                    };
+                }
+                ("hand", None) => parse_hand(&tokens[1..], &mut hands, i+1)?,
+                ("lead", None) => {
+                    game = Some(parse_lead(&rules, seed, &hands, &tokens[1..],
+                                           i+1)?);
+                }
+                ("play", Some(g)) => {
+                    moves += 1;
+                    replay_move(g, moves, &tokens[1..], i+1)?;
+                }
+                ("pass", Some(g)) => {
+                    moves += 1;
+                    let player = &tokens[1..tokens.len().min(2)];
+                    replay_move(g, moves, player, i+1)?;
+                }
+                ("turn", Some(g)) => {
+                    if tokens[1..].join(" ") != turn_str(g.turn()) {
+                        return Err(LoadError::Mismatch("turn"))
+                    }
+                }
+                ("last", Some(g)) => {
+                    let p = player().ok_or(invalid)?;
+                    let cards = parse_cards(&tokens[2..], &rules)?;
+                    match g.last_play {
+                        Some((q, ref last)) if p == q
+                            && last.cards() == cards.cards() => (),
+                        _ => return Err(LoadError::Mismatch("last play")),
+                    }
+                }
+                ("winners", Some(g)) => {
+                    let mut winners = vec![];
+                    for t in &tokens[1..] {
+                        winners.push(parse_player(t).ok_or(invalid.clone())?);
+                    }
+                    if winners != g.winners {
+                        return Err(LoadError::Mismatch("winners"))
+                    }
+                }
+                _ => return Err(invalid),
+            }
+        }
+        game.ok_or(LoadError::Line(s.lines().count() + 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let rules: RuleSet = "players=3 flush=rank".parse().unwrap();
+        let mut game = Game::with_seed(rules, 5);
+        for _ in 0..12 {
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            game.play(&moves[moves.len() / 2]).unwrap();
+        }
+        let saved = game.save();
+        let loaded = Game::load(&saved).unwrap();
+        assert_eq!(game.history(), loaded.history());
+        assert_eq!(Some(5), loaded.seed());
+        assert_eq!(saved, loaded.save());
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut game = Game::with_seed(RuleSet::default(), 5);
+        let p = game.turn().player();
+        let first = game.legal_moves(p)[0].clone();
+        game.play(&first).unwrap();
+        let saved = game.save();
+        let turn = format!("turn {}", turn_str(game.turn()));
+        let bad = saved.replace(&turn, &format!("turn #{} A", p));
+        assert!(matches!(Game::load(&bad), Err(LoadError::Mismatch("turn"))));
+        let bad = saved.replace("hand #2", "hand #3");
+        assert!(matches!(Game::load(&bad), Err(LoadError::Line(_))));
+    }
+}
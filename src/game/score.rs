@@ -0,0 +1,119 @@
+//! Penalty scoring for matches of several rounds.
+//!
+//! When a player empties their hand, everyone else is penalized for the
+//! cards they are left with. The player with the lowest total wins the
+//! match.
+
+use super::PlayerNum;
+
+/// The penalty for being left with `cards` cards: one point per card,
+/// doubled from 10 cards and tripled from 13.
+pub fn penalty(cards: usize) -> usize {
+    match cards {
+        0..=9 => cards,
+        10..=12 => cards*2,
+        _ => cards*3,
+    }
+}
+
+/// When a match is over.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MatchEnd {
+    /// After this many rounds.
+    Rounds(usize),
+    /// After the round where someone reaches this many points.
+    Target(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Match {
+    end: MatchEnd,
+    round: usize,
+    scores: Vec<usize>,
+}
+
+impl Match {
+    pub fn new(players: usize, end: MatchEnd) -> Match {
+        Match {
+            end,
+            round: 0,
+            scores: vec![0; players],
+        }
+    }
+
+    /// Resumes a match after `round` rounds with the given total
+    /// penalties.
+    pub fn from_scores(end: MatchEnd, round: usize, scores: Vec<usize>)
+                       -> Match {
+        Match {
+            end,
+            round,
+            scores,
+        }
+    }
+
+    /// When the match is over.
+    pub fn end(&self) -> MatchEnd {
+        self.end
+    }
+
+    /// Adds the penalties for a round, given how many cards each player
+    /// held when the first player went out.
+    pub fn add_round(&mut self, card_counts: &[usize]) {
+        for (score, &n) in self.scores.iter_mut().zip(card_counts) {
+            *score += penalty(n);
+        }
+        self.round += 1;
+    }
+
+    /// The number of rounds played so far.
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    /// Total penalties so far, in seat order.
+    pub fn scores(&self) -> &[usize] {
+        &self.scores
+    }
+
+    pub fn is_over(&self) -> bool {
+        match self.end {
+            MatchEnd::Rounds(n) => self.round >= n,
+            MatchEnd::Target(t) => self.scores.iter().any(|&s| s >= t),
+        }
+    }
+
+    /// Players from lowest to highest total penalty. Ties keep seat order.
+    pub fn ranking(&self) -> Vec<PlayerNum> {
+        let mut players: Vec<_> = (1..self.scores.len()+1).collect();
+        players.sort_by_key(|&p| self.scores[p-1]);
+        players
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn penalties() {
+        assert_eq!(0, penalty(0));
+        assert_eq!(9, penalty(9));
+        assert_eq!(20, penalty(10));
+        assert_eq!(39, penalty(13));
+    }
+
+    #[test]
+    fn match_to_target() {
+        let mut m = Match::new(3, MatchEnd::Target(30));
+        m.add_round(&[0, 5, 10]);
+        assert!(!m.is_over());
+        m.add_round(&[3, 0, 1]);
+        assert_eq!(&[3, 5, 21], m.scores());
+        assert!(!m.is_over());
+        m.add_round(&[13, 2, 0]);
+        assert!(m.is_over());
+        assert_eq!(3, m.round());
+        assert_eq!(vec![2, 3, 1], m.ranking());
+    }
+}
@@ -0,0 +1,333 @@
+//! Exact search with every hand in view, and reviews of finished games.
+//!
+//! A round is scored as soon as someone goes out, so the search asks
+//! whether a player can force going out first when everyone else plays
+//! against them. Positions are only solved within a node limit, which in
+//! practice means endgames.
+
+use std::fmt;
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use super::{Cards, Event, Game, Hand, PlayerNum, PlayerView, Turn};
+
+/// The search nodes `Solver::new` allows for each position.
+pub const DEFAULT_LIMIT: usize = 200_000;
+
+#[derive(Hash, Eq, PartialEq)]
+struct Key {
+    player: PlayerNum,
+    hands: Vec<Hand>,
+    curr_player: PlayerNum,
+    last_play: Option<(PlayerNum, Hand)>,
+    lead_lowest: bool,
+}
+
+/// Solves positions, keeping what it learns between them.
+pub struct Solver {
+    table: HashMap<Key, bool>,
+    limit: usize,
+    nodes: usize,
+}
+
+impl Default for Solver {
+    fn default() -> Solver {
+        Solver::new(DEFAULT_LIMIT)
+    }
+}
+
+impl Solver {
+    /// A solver that gives up on a position after `limit` search nodes.
+    pub fn new(limit: usize) -> Solver {
+        Solver {
+            table: HashMap::new(),
+            limit,
+            nodes: 0,
+        }
+    }
+
+    /// Whether `p` can go out first however the others play, or `None` if
+    /// that takes too long to find out.
+    pub fn solve(&mut self, game: &Game, p: PlayerNum) -> Option<bool> {
+        let mut game = game.clone();
+        // Searching only ever adds to the history.
+        game.history.truncate(1);
+        self.nodes = 0;
+        self.search(&game, p)
+    }
+
+    /// A move for the player whose turn it is that lets them force going
+    /// out first, if they can.
+    pub fn winning_move(&mut self, game: &Game) -> Option<Cards> {
+        let p = match game.turn() {
+            Turn::End => return None,
+            t => t.player(),
+        };
+        game.legal_moves(p).into_iter().find(|cards| {
+            let mut next = game.clone();
+            next.play(cards).unwrap();
+            self.solve(&next, p) == Some(true)
+        })
+    }
+
+    fn search(&mut self, game: &Game, p: PlayerNum) -> Option<bool> {
+        if let Some(&w) = game.winners.first() {
+            return Some(w == p)
+        }
+        let key = Key {
+            player: p,
+            hands: game.hands.clone(),
+            curr_player: game.curr_player,
+            last_play: game.last_play.as_ref()
+                .map(|&(q, ref cards)| (q, Hand::from(cards))),
+            lead_lowest: game.lead_lowest,
+        };
+        if let Some(&res) = self.table.get(&key) {
+            return Some(res)
+        }
+        self.nodes += 1;
+        if self.nodes > self.limit {
+            return None
+        }
+        let q = game.turn().player();
+        let mut moves = game.legal_moves(q);
+        // Going out ends the search, and bigger plays get there sooner.
+        moves.sort_by_key(|c| usize::MAX - c.len());
+        let mut res = q != p;
+        for cards in &moves {
+            let mut next = game.clone();
+            next.play(cards).unwrap();
+            // Whoever is to move picks what suits them: `p` looks for a way
+            // to win, everyone else for a way to stop them.
+            if self.search(&next, p)? == (q == p) {
+                res = q == p;
+                break
+            }
+        }
+        self.table.insert(key, res);
+        Some(res)
+    }
+}
+
+/// How likely moves are to let a player force going out first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chances {
+    /// For each move, the share of deals in which it does.
+    pub wins: Vec<f64>,
+    /// Whether the other hands were known, so that every move certainly
+    /// wins or certainly doesn't.
+    pub exact: bool,
+}
+
+/// For each of `moves`, the share of `deals` random deals of the unseen
+/// cards in which playing it lets `view.player` force going out first.
+/// A single deal is enough when all the unseen cards are with one opponent.
+/// `None` if a position is too big to solve.
+pub fn win_chances<R: Rng>(view: &PlayerView, moves: &[Cards], deals: usize,
+                           solver: &mut Solver, rng: &mut R)
+                           -> Option<Chances> {
+    let held: Vec<_> = view.card_counts.iter().enumerate()
+        .filter(|&(i, &n)| i+1 != view.player && n > 0)
+        .map(|(_, &n)| n)
+        .collect();
+    let unseen = 52 - view.hand.len() - view.discard_pile().len();
+    let exact = match held[..] {
+        [] => true,
+        [n] => n == unseen,
+        _ => false,
+    };
+    let deals = if exact { 1 } else { deals };
+    let mut wins = vec![0; moves.len()];
+    for _ in 0..deals {
+        let game = view.determinize(rng)?;
+        for (i, cards) in moves.iter().enumerate() {
+            let mut next = game.clone();
+            next.play(cards).ok()?;
+            if solver.solve(&next, view.player)? {
+                wins[i] += 1;
+            }
+        }
+    }
+    Some(Chances {
+        wins: wins.into_iter().map(|w| w as f64 / deals as f64).collect(),
+        exact,
+    })
+}
+
+/// A move that changed who could force going out first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MistakeKind {
+    /// The player could have forced going out first with these cards.
+    MissedWin(Cards),
+    /// The play lets this other player force going out first.
+    GaveWin(PlayerNum),
+}
+
+/// A move in a reviewed game that made things worse for whoever made it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mistake {
+    /// From 1, counting plays and passes.
+    pub number: usize,
+    pub player: PlayerNum,
+    pub played: Cards,
+    pub kind: MistakeKind,
+}
+
+impl fmt::Display for Mistake {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Move {}: #{} ", self.number, self.player)?;
+        if self.played.is_pass() {
+            write!(f, "passed")?;
+        } else {
+            write!(f, "played {}", self.played)?;
+        }
+        match self.kind {
+            MistakeKind::MissedWin(ref cards) if cards.is_pass() => {
+                write!(f, ", but passing would have won")
+            }
+            MistakeKind::MissedWin(ref cards) => {
+                write!(f, ", but {} would have won", cards)
+            }
+            MistakeKind::GaveWin(q) => {
+                write!(f, ", which lets #{} win", q)
+            }
+        }
+    }
+}
+
+/// The mistakes found in a game, and how many moves were too early in the
+/// game to be solved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    pub mistakes: Vec<Mistake>,
+    pub unsolved: usize,
+}
+
+/// How many moves were made before someone went out.
+fn until_out(game: &Game) -> usize {
+    let mut n = 0;
+    for event in game.history() {
+        match *event {
+            Event::Play(..) | Event::Pass(_) => n += 1,
+            Event::Win(_) => break,
+            _ => (),
+        }
+    }
+    n
+}
+
+/// Reviews the moves of `game` up to the first player going out, from the
+/// last move back, until the positions get too big to solve.
+pub fn analyze(game: &Game, solver: &mut Solver) -> Analysis {
+    let moves = game.moves();
+    let played = until_out(game);
+    let mut mistakes = vec![];
+    let mut after = game.replay(played);
+    for i in (0..played).rev() {
+        let before = game.replay(i);
+        let (p, ref cards) = moves[i];
+        let (mut missed, mut gave) = (None, None);
+        for q in 1..game.players()+1 {
+            let (was, is) = match (solver.solve(&before, q),
+                                   solver.solve(&after, q)) {
+                (Some(was), Some(is)) => (was, is),
+                _ => {
+                    mistakes.reverse();
+                    return Analysis { mistakes, unsolved: i + 1 }
+                }
+            };
+            if q == p && was && !is {
+                let best = solver.winning_move(&before).unwrap();
+                missed = Some(MistakeKind::MissedWin(best));
+            } else if q != p && !was && is {
+                gave = Some(MistakeKind::GaveWin(q));
+            }
+        }
+        if let Some(kind) = missed.or(gave) {
+            mistakes.push(Mistake {
+                number: i + 1,
+                player: p,
+                played: cards.clone(),
+                kind,
+            });
+        }
+        after = before;
+    }
+    mistakes.reverse();
+    Analysis { mistakes, unsolved: 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, Isaac64Rng};
+    use game::RuleSet;
+
+    /// A game where everyone always plays their lowest move.
+    fn lowest_first(rules: &str, seed: u64) -> Game {
+        let rules: RuleSet = rules.parse().unwrap();
+        let mut game = Game::with_seed(rules, seed);
+        while game.turn() != Turn::End {
+            let p = game.turn().player();
+            let cards = game.legal_moves(p)[0].clone();
+            game.play(&cards).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn endgame() {
+        let game = lowest_first("players=2 deal=13", 8);
+        let n = until_out(&game);
+        let winner = game.winners()[0];
+        // Whoever is about to go out has won.
+        let last = game.replay(n - 1);
+        let mut solver = Solver::new(20_000);
+        assert_eq!(Some(true), solver.solve(&last, winner));
+        assert_eq!(Some(false), solver.solve(&last, 3 - winner));
+        assert_eq!(game.moves()[n-1].1.cards(),
+                   solver.winning_move(&last).unwrap().cards());
+        // Nobody can see that far ahead from the deal.
+        assert_eq!(None, solver.solve(&game.replay(0), winner));
+    }
+
+    #[test]
+    fn mistakes() {
+        let game = lowest_first("players=2 deal=13", 8);
+        let analysis = analyze(&game, &mut Solver::new(20_000));
+        assert_eq!(0, analysis.unsolved);
+        let strs: Vec<_> = analysis.mistakes.iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(vec!["Move 5: #2 played QS QD, but passing would have won",
+                        "Move 10: #1 played 5D, but 8D would have won"],
+                   strs);
+    }
+
+    #[test]
+    fn chances() {
+        let game = lowest_first("players=2", 8);
+        let n = until_out(&game);
+        let (winner, ref out) = game.moves()[n-1];
+        let view = game.replay(n - 1).view(winner);
+        let moves = view.legal_moves();
+        let mut rng = Isaac64Rng::from_seed(&[1]);
+        let chances = win_chances(&view, &moves, 10, &mut Solver::new(20_000),
+                                  &mut rng).unwrap();
+        let i = moves.iter().position(|c| c.cards() == out.cards()).unwrap();
+        assert_eq!(1.0, chances.wins[i]);
+        // With the whole deck dealt to two players, the opponent's cards are
+        // known, so every chance is exact.
+        assert!(chances.exact);
+        assert!(chances.wins.iter().all(|&c| c == 0.0 || c == 1.0));
+        // Not so with cards left over from the deal.
+        let game = lowest_first("players=2 deal=13", 8);
+        let n = until_out(&game);
+        let (winner, _) = game.moves()[n-1];
+        let view = game.replay(n - 1).view(winner);
+        let chances = win_chances(&view, &view.legal_moves(), 10,
+                                  &mut Solver::new(20_000), &mut rng).unwrap();
+        assert!(!chances.exact);
+    }
+}
@@ -0,0 +1,201 @@
+//! What a single player can see of a game.
+//!
+//! A `PlayerView` is built up from the same things a client is told over
+//! the network: the rules, the deal, and every turn, play and win. The
+//! engine produces one with `Game::view`, so bots see the same whether
+//! they run locally or over the network.
+
+use rand::Rng;
+
+use super::{hand, Card, Cards, Deal, Game, GameError, Hand, PlayerNum,
+            RuleSet, Turn};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerView {
+    pub player: PlayerNum,
+    pub rules: RuleSet,
+    /// From lowest to highest.
+    pub hand: Vec<Card>,
+    /// How many cards each player holds, in seat order.
+    pub card_counts: Vec<usize>,
+    /// Every play and pass so far, in order, with passes as
+    /// `Cards::pass()`.
+    pub moves: Vec<(PlayerNum, Cards)>,
+    /// Who has passed since the current trick started.
+    pub passed: Vec<PlayerNum>,
+    /// Players who have emptied their hands, from first to last.
+    pub winners: Vec<PlayerNum>,
+    pub turn: Option<Turn>,
+    /// The last non-pass play and who made it.
+    pub last_play: Option<(PlayerNum, Cards)>,
+}
+
+impl PlayerView {
+    /// The view of `player` after being dealt `hand`. Until told otherwise,
+    /// any cards left over from a full deal are assumed to be with whoever
+    /// holds the lowest card, or with nobody if it is not `player`.
+    pub fn new(player: PlayerNum, rules: RuleSet, mut hand: Vec<Card>)
+               -> PlayerView {
+        rules.sort(&mut hand);
+        let mut card_counts = vec![rules.hand_size(); rules.players];
+        if player > 0 {
+            card_counts[player-1] = hand.len();
+        }
+        PlayerView {
+            player,
+            rules,
+            hand,
+            card_counts,
+            moves: vec![],
+            passed: vec![],
+            winners: vec![],
+            turn: None,
+            last_play: None,
+        }
+    }
+
+    /// The cards player `p` has played so far, in order.
+    pub fn discards(&self, p: PlayerNum) -> Vec<Card> {
+        self.moves.iter()
+            .filter(|&&(q, _)| q == p)
+            .flat_map(|(_, cards)| cards.cards().iter().cloned())
+            .collect()
+    }
+
+    /// Every card played so far, in order.
+    pub fn discard_pile(&self) -> Vec<Card> {
+        self.moves.iter()
+            .flat_map(|(_, cards)| cards.cards().iter().cloned())
+            .collect()
+    }
+
+    pub fn set_turn(&mut self, turn: Turn) {
+        if let Turn::Start(p) = turn {
+            // Whoever starts holds the lowest card and any left over.
+            if self.rules.deal == Deal::Full && self.moves.is_empty() {
+                let leftover = 52 % self.rules.players;
+                if p != self.player {
+                    self.card_counts[p-1] = self.rules.hand_size() + leftover;
+                }
+            }
+        }
+        if let Turn::Any(_) = turn {
+            if !self.moves.is_empty() {
+                self.passed.clear();
+            }
+        }
+        self.turn = Some(turn);
+    }
+
+    /// Records a play or pass by `p`. Fails, leaving the view as it was,
+    /// if `p` is known not to hold the cards.
+    pub fn add_play(&mut self, p: PlayerNum, cards: Cards)
+                    -> Result<(), GameError> {
+        if cards.is_pass() {
+            self.passed.push(p);
+        } else {
+            let left = self.card_counts[p-1].checked_sub(cards.len())
+                .ok_or(GameError::NotInHand)?;
+            if p == self.player {
+                if !cards.cards().iter().all(|c| self.hand.contains(c)) {
+                    return Err(GameError::NotInHand)
+                }
+                self.hand.retain(|c| !cards.contains(c));
+            }
+            self.card_counts[p-1] = left;
+            self.last_play = Some((p, cards.clone()));
+        }
+        self.moves.push((p, cards));
+        Ok(())
+    }
+
+    pub fn add_win(&mut self, p: PlayerNum) {
+        self.winners.push(p);
+    }
+
+    /// Everything this player may play now, including passing when
+    /// allowed. Empty if it is not their turn.
+    pub fn legal_moves(&self) -> Vec<Cards> {
+        let turn = match self.turn {
+            Some(Turn::End) | None => return vec![],
+            Some(t) if t.player() != self.player => return vec![],
+            Some(t) => t,
+        };
+        let hand: Hand = self.hand.iter().cloned().collect();
+        match turn {
+            Turn::Start(_) => {
+                let mut moves = hand::combinations(hand, &self.rules, None);
+                // Whoever starts holds the lowest card that was dealt.
+                moves.retain(|c| c.contains(&self.hand[0]));
+                moves
+            }
+            Turn::Follow(_) => {
+                let last = self.last_play.as_ref().map(|l| &l.1);
+                let mut moves = hand::combinations(hand, &self.rules, last);
+                moves.push(Cards::pass());
+                moves
+            }
+            _ => hand::combinations(hand, &self.rules, None),
+        }
+    }
+
+    /// What to play once a chosen move was turned down: a pass if
+    /// allowed, or else the fewest and lowest cards that may be played.
+    /// `None` if it is not this player's turn.
+    pub fn fallback_move(&self) -> Option<Cards> {
+        self.legal_moves().into_iter()
+            .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+    }
+
+    /// The cards this player hasn't seen: those the others hold, and any
+    /// that weren't dealt.
+    fn unseen(&self) -> Hand {
+        let mut seen: Hand = self.hand.iter().cloned().collect();
+        seen = seen | self.discard_pile().into_iter().collect();
+        (0..52).map(hand::card_at).filter(|&c| !seen.contains(c)).collect()
+    }
+
+    /// Whether an opponent may hold cards that beat `cards`, going by the
+    /// unseen cards and how many each opponent has left.
+    pub fn can_be_beaten(&self, cards: &Cards) -> bool {
+        if cards.is_pass() {
+            return false
+        }
+        let enough = self.card_counts.iter().enumerate()
+            .any(|(i, &n)| i+1 != self.player && n >= cards.len());
+        enough && !hand::combinations(self.unseen(), &self.rules, Some(cards))
+            .is_empty()
+    }
+
+    /// A game that this could be a view of, with the unseen cards dealt at
+    /// random. `None` if the cards can't be dealt that way.
+    pub fn determinize<R: Rng>(&self, rng: &mut R) -> Option<Game> {
+        let mut pool = self.unseen().cards();
+        rng.shuffle(&mut pool);
+        let mut hands = vec![];
+        for p in 1..self.rules.players+1 {
+            let mut cards = if p == self.player {
+                self.hand.clone()
+            } else {
+                let n = self.card_counts[p-1];
+                if n > pool.len() {
+                    return None
+                }
+                pool.drain(..n).collect()
+            };
+            cards.extend(self.discards(p));
+            hands.push(cards);
+        }
+        let mut game = Game::from_hands(self.rules.clone(), hands).ok()?;
+        match (self.moves.first(), self.turn) {
+            (Some(&(p, _)), _) | (None, Some(Turn::Any(p))) => {
+                game.set_leader(p)
+            }
+            _ => (),
+        }
+        for (_, cards) in &self.moves {
+            game.play(cards).ok()?;
+        }
+        Some(game)
+    }
+}
@@ -3,6 +3,8 @@ extern crate termion;
 
 pub mod client;
 pub mod server;
-mod game;
+pub mod game;
+pub mod strategy;
+pub mod tournament;
 mod bots;
 mod utils;
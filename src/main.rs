@@ -1,28 +1,294 @@
 extern crate baraha;
 
 use std::env;
+use std::fs;
+use std::process;
+use std::str::FromStr;
 
-use baraha::{server, client};
+use baraha::{server, client, strategy, tournament};
+use baraha::client::Seat;
+use baraha::game::{Game, MatchEnd, RuleSet};
+use baraha::game::record::Record;
+use baraha::game::solver::{self, Solver};
+
+const USAGE: &str = "Usage:
+  baraha host [--bind=ADDR | --port=N] [--seed=N] [--rounds=N | --target=N]
+              [--checkpoint=FILE] [--records=DIR] [--bots=N]
+              [--strategy=SPEC] [RULE=VALUE ..]
+  baraha tables HOST[:PORT]
+  baraha play HOST[:PORT] [--table=NAME | --rejoin=TOKEN]
+  baraha bot HOST[:PORT] [--table=NAME | --rejoin=TOKEN] [--strategy=SPEC]
+  baraha tournament [SPEC ..] [--games=N] [--threads=N] [--seed=N]
+                    [--duplicate=yes|no] [RULE=VALUE ..]
+  baraha replay FILE
+  baraha analyze FILE [--limit=N]
+  baraha help
+
+Options take their value after = or a space, as in --bots 3. Rules are
+as in `R` messages, e.g. players=3 deal=13. The server listens on
+0.0.0.0:2222 and writes game records to ./records unless told otherwise;
+--records= with no directory turns them off. --bots fills the first
+seats of every table with the server's own bots, which play with
+--strategy (greedy by default), as do players who lose their connection
+until they are back. Players join the table called main unless they name
+another, or take back the seat they lost with the token they were given.
+Strategies are greedy, planner and ismcts, which takes options like
+ismcts,iterations=500,seed=1.";
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() < 2 {
-        panic!("invalid usage")
-    }
-    match &*args[1] {
-        "host" => server::host(),
-        "play" => {
-            if args.len() != 3 {
-                panic!("invalid usage")
-            }
-            client::play(args[2].clone());
+    let args: Vec<_> = env::args().skip(1).collect();
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => fail("no command given"),
+    };
+    let res = match command {
+        "host" => host(args),
+        "tables" => tables(args),
+        "play" => play(args),
+        "bot" => bot(args),
+        "tournament" => run_tournament(args),
+        "replay" => replay(args),
+        "analyze" => analyze(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("unknown command {}", command)),
+    };
+    if let Err(msg) = res {
+        fail(&msg);
+    }
+}
+
+/// Explains what is wrong with the command line and exits.
+fn fail(msg: &str) -> ! {
+    eprintln!("baraha: {}\n\n{}", msg, USAGE);
+    process::exit(2)
+}
+
+/// Options given as `--option=VALUE` or `--option VALUE`.
+type Flags = Vec<(String, String)>;
+
+/// The options in `args`, and the other arguments.
+fn split_options(args: &[String]) -> Result<(Flags, Vec<String>), String> {
+    let mut options = vec![];
+    let mut rest = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            rest.push(arg.clone());
+            continue
         }
-        "bot" => {
-            if args.len() != 3 {
-                panic!("invalid usage")
+        let option = match arg.find('=') {
+            Some(i) => (arg[..i].to_string(), arg[i+1..].to_string()),
+            None => match args.next() {
+                Some(value) => (arg.clone(), value.clone()),
+                None => return Err(format!("{} needs a value", arg)),
+            },
+        };
+        options.push(option);
+    }
+    Ok((options, rest))
+}
+
+fn number<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("{} needs a number", key))
+}
+
+fn yes_no(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "yes" => Ok(true),
+        "no" => Ok(false),
+        _ => Err(format!("{} needs yes or no", key)),
+    }
+}
+
+/// The rules given as `RULE=VALUE` arguments.
+fn parse_rules(rules: &[String]) -> Result<RuleSet, String> {
+    if let Some(arg) = rules.iter().find(|r| !r.contains('=')) {
+        return Err(format!("unexpected argument {}", arg))
+    }
+    rules.join(" ").parse().map_err(|e| format!("invalid rules: {}", e))
+}
+
+fn read(path: &str) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))
+}
+
+/// The single argument that `args` should have.
+fn one(args: &[String], what: &str) -> Result<String, String> {
+    match args {
+        [arg] => Ok(arg.clone()),
+        [] => Err(format!("no {} given", what)),
+        _ => Err("too many arguments".into()),
+    }
+}
+
+fn unknown_option(option: &str) -> String {
+    format!("unknown option {}", option)
+}
+
+fn host(args: &[String]) -> Result<(), String> {
+    let mut addr = format!("0.0.0.0:{}", server::DEFAULT_PORT);
+    let mut options = server::Options {
+        records: Some("records".into()),
+        ..server::Options::default()
+    };
+    let (flags, rules) = split_options(args)?;
+    for (key, v) in &flags {
+        match key.as_str() {
+            "--bind" => addr = v.clone(),
+            "--port" => {
+                addr = format!("0.0.0.0:{}", number::<u16>(key, v)?)
             }
-            client::bot(args[2].clone());
+            "--seed" => options.seed = Some(number(key, v)?),
+            "--rounds" => options.end = MatchEnd::Rounds(number(key, v)?),
+            "--target" => options.end = MatchEnd::Target(number(key, v)?),
+            "--checkpoint" => options.checkpoint = Some(v.into()),
+            "--records" if v.is_empty() => options.records = None,
+            "--records" => options.records = Some(v.into()),
+            "--bots" => options.bots = number(key, v)?,
+            "--strategy" => options.strategy = v.clone(),
+            _ => return Err(unknown_option(key)),
         }
-        _ => panic!("invalid usage")
     }
+    options.rules = parse_rules(&rules)?;
+    if strategy::by_name(&options.strategy).is_none() {
+        return Err(unknown_strategy(&options.strategy))
+    }
+    if options.bots >= options.rules.players {
+        return Err("tables need a seat that isn't a bot".into())
+    }
+    let server = server::Server::bind(&*addr)
+        .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    if let Ok(addr) = server.local_addr() {
+        println!("Listening on {}.", addr);
+    }
+    server.with_options(options).run();
+    Ok(())
+}
+
+fn tables(args: &[String]) -> Result<(), String> {
+    let host = one(args, "host")?;
+    let tables = client::tables(&host)
+        .map_err(|e| format!("cannot list the tables of {}: {}", host, e))?;
+    if tables.is_empty() {
+        println!("No tables yet.");
+    }
+    for table in tables {
+        println!("{} ({} of {} players)", table.name, table.joined,
+                 table.players);
+    }
+    Ok(())
+}
+
+fn play(args: &[String]) -> Result<(), String> {
+    let (flags, rest) = split_options(args)?;
+    let host = one(&rest, "host")?;
+    let mut seat = Seat::Table(server::DEFAULT_TABLE.into());
+    for (key, v) in flags {
+        match key.as_str() {
+            "--table" => seat = Seat::Table(v),
+            "--rejoin" => seat = Seat::Back(v),
+            _ => return Err(unknown_option(&key)),
+        }
+    }
+    client::play(host, seat);
+    Ok(())
+}
+
+fn bot(args: &[String]) -> Result<(), String> {
+    let (flags, rest) = split_options(args)?;
+    let host = one(&rest, "host")?;
+    let mut seat = Seat::Table(server::DEFAULT_TABLE.into());
+    let mut name = "greedy".to_string();
+    for (key, v) in flags {
+        match key.as_str() {
+            "--table" => seat = Seat::Table(v),
+            "--rejoin" => seat = Seat::Back(v),
+            "--strategy" => name = v,
+            _ => return Err(unknown_option(&key)),
+        }
+    }
+    let strategy = strategy::by_name(&name)
+        .ok_or_else(|| unknown_strategy(&name))?;
+    client::bot_with(host, seat, strategy);
+    Ok(())
+}
+
+fn unknown_strategy(name: &str) -> String {
+    format!("unknown strategy {}, try one of: {}",
+            name, strategy::NAMES.join(", "))
+}
+
+fn run_tournament(args: &[String]) -> Result<(), String> {
+    let mut options = tournament::Options::default();
+    let (flags, rest) = split_options(args)?;
+    for (key, v) in &flags {
+        match key.as_str() {
+            "--games" => options.games = number(key, v)?,
+            "--threads" => options.threads = number(key, v)?,
+            "--seed" => options.seed = number(key, v)?,
+            "--duplicate" => options.duplicate = yes_no(key, v)?,
+            _ => return Err(unknown_option(key)),
+        }
+    }
+    let mut strategies = vec![];
+    let mut rules = vec![];
+    for arg in rest {
+        if strategy::by_name(&arg).is_some() {
+            strategies.push(arg);
+        } else if arg.contains(',') || !arg.contains('=') {
+            // Rules never have options, the way strategies do.
+            return Err(unknown_strategy(&arg))
+        } else {
+            rules.push(arg);
+        }
+    }
+    if !strategies.is_empty() {
+        options.strategies = strategies;
+    }
+    options.rules = parse_rules(&rules)?;
+    print!("{}", tournament::run(&options));
+    Ok(())
+}
+
+fn replay(args: &[String]) -> Result<(), String> {
+    let path = one(args, "record")?;
+    let record: Record = read(&path)?.parse()
+        .map_err(|e| format!("invalid record {}: {}", path, e))?;
+    client::replay(record);
+    Ok(())
+}
+
+fn analyze(args: &[String]) -> Result<(), String> {
+    let (flags, rest) = split_options(args)?;
+    let path = one(&rest, "log")?;
+    let mut limit = solver::DEFAULT_LIMIT;
+    for (key, v) in &flags {
+        match key.as_str() {
+            "--limit" => limit = number(key, v)?,
+            _ => return Err(unknown_option(key)),
+        }
+    }
+    let log = read(&path)?;
+    // Either a game record or a saved game.
+    let game = if log.starts_with('[') {
+        log.parse::<Record>().map(|r| r.game)
+    } else {
+        Game::load(&log)
+    };
+    let game = game.map_err(|e| format!("invalid log {}: {}", path, e))?;
+    let analysis = solver::analyze(&game, &mut Solver::new(limit));
+    for mistake in &analysis.mistakes {
+        println!("{}", mistake);
+    }
+    if analysis.mistakes.is_empty() {
+        println!("No mistakes found.");
+    }
+    if analysis.unsolved > 0 {
+        println!("The first {} moves were too early to solve.",
+                 analysis.unsolved);
+    }
+    Ok(())
 }
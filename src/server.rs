@@ -1,83 +1,643 @@
 //! # Protocol
 //!
+//! Players first join a table in the lobby. Each table plays its own match
+//! once as many players as the rules ask for have joined.
+//!
+//! ## Lobby
+//! * `L` - List: sent by the client to list the tables
+//! * `L [{T} {J}/{N} ..]` - Tables: every table T, with J of its N players
+//!   joined
+//! * `J {T} [{K}={V} ..]` - Join: sent by the client to join table T,
+//!   making it with rules K=V.. if there is no such table
+//! * `J {T} {J}/{N}` - Joined: you are player J of N at table T
+//! * `B {K}` - Back: sent by the client to take back the seat that was
+//!   given token K, after losing the connection
+//! * `? {M}` - The table can't be joined: where M is why
+//!
+//! Players who come back are sent `U` again and, in the middle of a round,
+//! everything needed to pick it up: `R`, their `D`, `S` after the first
+//! round, `C` as dealt, every `P` and `W` since the deal (passes only for
+//! the current trick), then `C` and `T`. While a player is away the others
+//! are told with `!`, and a bot plays for them.
+//!
+//! The server can also fill the first seats of every table with its own
+//! bots, which count as joined from the start.
+//!
 //! ## Server to Client
-//! * `U #{N}` - You: where N is your player number
+//! * `U #{N} {K}` - You: where N is your player number and K is a token to
+//!   get the seat back with
 //! * `D [{C} ..]` - Deal: where C.. is a list of space-separated cards
 //! * `? {M}` - Invalid input: where M is message
 //!
 //! ## Server to All
+//! * `R {K}={V} ..` - Rules: the house rules in effect, see `game::rules`
 //! * `! {M}` - Error: where M is message
 //! * `P #{N} [{C} ..]` - Play: N played C..
 //! * `T #{N} [S|F|A]` - Turn: N's turn -- S to start, F to follow, A to any
 //! * `W #{N}` - Win: where N emptied their hand
-//! * `E [#{N} ..]` - End: where N.. is a list of winners (from 1st to 3rd)
+//! * `C [{N} ..]` - Counts: where N.. is the number of cards each player
+//!   holds, in seat order, sent after the deal and after every play
+//! * `E [#{N} ..]` - End: where N.. is a list of winners (from 1st to the
+//!   second to last player)
+//! * `S {R} [{S} ..]` - Score: where S.. are the total penalties of each
+//!   player after round R
+//! * `M [#{N} ..]` - Match end: where N.. is a list of players from lowest
+//!   to highest total penalty
 //!
 //! ## Client to Server
 //! * `G` - Game: ready for game
 //! * `P [{C} ..]` - Play: play C..
 
+use std::fs;
+use std::io;
 use std::io::prelude::*;
-use std::net::{TcpListener, TcpStream};
+use std::collections::VecDeque;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+
+use rand::{self, Rng};
 
 use bots::dealer::{self, Output};
+pub use bots::dealer::Options;
 use game;
+use game::record::Record;
+use strategy::{self, Strategy};
 use utils;
 
+/// The port servers listen on and clients connect to unless told otherwise.
+pub const DEFAULT_PORT: u16 = 2222;
+
+/// The table players join unless they name another.
+pub const DEFAULT_TABLE: &str = "main";
+
+/// A lobby where players join tables by name, and every table that fills
+/// up plays its own match.
+pub struct Server {
+    listener: TcpListener,
+    options: Options,
+}
+
+/// A table that is waiting for players or playing.
+struct Table {
+    name: String,
+    rules: game::RuleSet,
+    players: usize,
+    /// The reconnect token of every player seated so far, empty for the
+    /// server's bots.
+    tokens: Vec<String>,
+    /// How many seats have been told they joined, counting the server's
+    /// bots. The match starts once all of them have, so that nobody hears
+    /// from it first.
+    told: usize,
+    /// Where players who come back are handed to the match.
+    sender: Sender<Message>,
+    /// Taken once the table is full and the match starts.
+    host: Option<Host>,
+}
+
+/// What a match hears from its players.
+enum Message {
+    /// A line from the player in a seat, over the connection with this
+    /// number.
+    Line(game::PlayerNum, usize, String),
+    /// The connection with this number was closed.
+    Gone(game::PlayerNum, usize),
+    /// The player came back over a new connection.
+    Back(game::PlayerNum, TcpStream),
+}
+
+/// What a seat does when it is asked to.
+enum Move {
+    /// A line its player sent.
+    Line(String),
+    /// The cards its stand-in plays.
+    Cards(game::Cards),
+}
+
+/// A player's place at a table.
+struct Seat {
+    stream: Option<TcpStream>,
+    /// Counts the connections the seat has had, so that lines still coming
+    /// from an old one are ignored.
+    conn: usize,
+    /// Whether the player has been told their number.
+    greeted: bool,
+    /// Lines that came in before the dealer asked for them.
+    pending: VecDeque<String>,
+    /// Plays whenever nobody is connected to the seat.
+    stand_in: Box<dyn Strategy>,
+    /// Whether the seat's last move was turned down, so that the stand-in
+    /// falls back on a move that can't be.
+    rejected: bool,
+    /// Who sat down, for game records.
+    name: String,
+}
+
+/// Plays a table's match with the players in its seats.
+struct Host {
+    name: String,
+    dealer: dealer::Dealer,
+    seats: Vec<Seat>,
+    tokens: Vec<String>,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+    checkpoint: Option<PathBuf>,
+    records: Option<PathBuf>,
+    strategy: String,
+}
+
+type Lobby = Arc<Mutex<Vec<Table>>>;
+
+/// Takes a table out of the lobby when its match is over, however it ends.
+struct Leave(Lobby, String);
+
+impl Drop for Leave {
+    fn drop(&mut self) {
+        if let Ok(mut tables) = self.0.lock() {
+            tables.retain(|t| t.name != self.1);
+        }
+    }
+}
+
+impl Server {
+    /// Listens on `addr`. With port 0 any free port is used, which
+    /// `local_addr` tells.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            options: Options::default(),
+        })
+    }
+
+    /// The options every table starts with, apart from the rules given
+    /// when the table is made.
+    pub fn with_options(mut self, options: Options) -> Server {
+        self.options = options;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Lets players in until the process is stopped.
+    pub fn run(self) {
+        let lobby: Lobby = Arc::new(Mutex::new(vec![]));
+        println!("Waiting for players.");
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+            let (lobby, options) = (lobby.clone(), self.options.clone());
+            thread::spawn(move || greet(stream, &lobby, &options));
+        }
+    }
+}
+
 pub fn host() {
-    let listener = TcpListener::bind("0.0.0.0:2222").unwrap();
+    host_with(Options::default())
+}
+
+/// Hosts a lobby on every interface, on the default port.
+pub fn host_with(options: Options) {
+    Server::bind(("0.0.0.0", DEFAULT_PORT)).expect("cannot listen")
+        .with_options(options)
+        .run();
+}
+
+/// Answers a new player until they sit at a table.
+fn greet(mut stream: TcpStream, lobby: &Lobby, options: &Options) {
+    while let Some(line) = utils::try_read_line(&stream) {
+        let tokens: Vec<_> = line.split_whitespace().collect();
+        let res = match tokens.split_first() {
+            Some((&"L", [])) => {
+                let mut reply = "L".to_string();
+                for table in lobby.lock().unwrap().iter() {
+                    reply += &format!(" {} {}/{}", table.name,
+                                      table.tokens.len(), table.players);
+                }
+                stream.write_all((reply + "\r\n").as_bytes())
+                    .map_err(|_| String::new())
+            }
+            Some((&"J", [name, rules @ ..])) => {
+                match stream.try_clone() {
+                    Ok(seat) => match join(lobby, name, rules, seat, options) {
+                        Ok(()) => return,
+                        Err(msg) => Err(msg),
+                    },
+                    Err(_) => return,
+                }
+            }
+            Some((&"B", [token])) => {
+                match stream.try_clone() {
+                    Ok(seat) => match rejoin(lobby, token, seat) {
+                        Ok(()) => return,
+                        Err(msg) => Err(msg),
+                    },
+                    Err(_) => return,
+                }
+            }
+            _ => Err("expected L, J or B".into()),
+        };
+        if let Err(msg) = res {
+            if stream.write_all(format!("? {}\r\n", msg).as_bytes()).is_err() {
+                return
+            }
+        }
+    }
+}
+
+/// Seats the player on `stream` at table `name`, making the table with
+/// `rules` if there isn't one yet, and starts its match once it is full.
+fn join(lobby: &Lobby, name: &str, rules: &[&str], mut stream: TcpStream,
+        options: &Options) -> Result<(), String> {
+    let rules = if rules.is_empty() {
+        None
+    } else {
+        Some(rules.join(" ").parse::<game::RuleSet>()
+             .map_err(|e| e.to_string())?)
+    };
+    let checkpoint = options.checkpoint.as_ref()
+        .map(|path| table_file(path, name));
+    // Files are read before taking the lobby, in case the table is new.
+    let exists = lobby.lock().unwrap().iter().any(|t| t.name == name);
+    let saved = match checkpoint {
+        Some(ref path) if !exists && path.exists() => {
+            Some(fs::read_to_string(path).map_err(|e| e.to_string())?)
+        }
+        _ => None,
+    };
+    let stand_in = stand_in(options)?;
+    let reply = {
+        let mut tables = lobby.lock().unwrap();
+        let i = match tables.iter().position(|t| t.name == name) {
+            Some(i) => {
+                let table = &tables[i];
+                if table.tokens.len() == table.players {
+                    return Err(format!("table {} is playing", name))
+                }
+                if rules.as_ref().is_some_and(|r| *r != table.rules) {
+                    return Err(format!("table {} has other rules", name))
+                }
+                i
+            }
+            None => {
+                tables.push(new_table(name, rules, saved, checkpoint,
+                                      options)?);
+                tables.len() - 1
+            }
+        };
+        let table = &mut tables[i];
+        let token = format!("{:016x}", rand::thread_rng().gen::<u64>());
+        table.tokens.push(token.clone());
+        let seat = stream.try_clone().map_err(|e| e.to_string())?;
+        table.host.as_mut().unwrap().sit(Some(seat), token, stand_in);
+        format!("J {} {}/{}\r\n", name, table.tokens.len(), table.players)
+    };
+    // A player who is gone already is noticed once the match starts.
+    stream.write_all(reply.as_bytes()).ok();
+    let mut tables = lobby.lock().unwrap();
+    let table = tables.iter_mut().find(|t| t.name == name).unwrap();
+    table.told += 1;
+    if table.told < table.players {
+        if table.tokens.len() < table.players {
+            println!("Table {}: waiting for {} player(s).", name,
+                     table.players - table.tokens.len());
+        }
+        return Ok(())
+    }
+    println!("Table {}: game!", name);
+    let host = table.host.take().unwrap();
+    let leave = Leave(lobby.clone(), name.into());
+    thread::spawn(move || {
+        let _leave = leave;
+        host.run();
+    });
+    Ok(())
+}
 
-    println!("Waiting for 4 players.");
+/// A table called `name` that resumes the match `saved` at `checkpoint`, or
+/// starts a new one with `rules`.
+fn new_table(name: &str, rules: Option<game::RuleSet>, saved: Option<String>,
+             checkpoint: Option<PathBuf>, options: &Options)
+             -> Result<Table, String> {
+    let mut options = options.clone();
+    if let Some(rules) = rules {
+        options.rules = rules;
+    }
+    let dealer = match saved {
+        Some(text) => {
+            let dealer = dealer::restore(&text).map_err(|e| e.to_string())?;
+            if let Some(ref path) = checkpoint {
+                println!("Resuming the match in {}.", path.display());
+            }
+            dealer
+        }
+        None => dealer::with_options(options.clone()),
+    };
+    let rules = dealer.options().rules.clone();
+    if options.bots >= rules.players {
+        return Err(format!("table {} would only have bots", name))
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut host = Host {
+        name: name.into(),
+        dealer,
+        seats: vec![],
+        tokens: vec![],
+        sender: sender.clone(),
+        receiver,
+        checkpoint,
+        records: options.records.clone(),
+        strategy: options.strategy.clone(),
+    };
+    for _ in 0..options.bots {
+        host.sit(None, String::new(), stand_in(&options)?);
+    }
+    Ok(Table {
+        name: name.into(),
+        players: rules.players,
+        rules,
+        tokens: vec![String::new(); options.bots],
+        told: options.bots,
+        sender,
+        host: Some(host),
+    })
+}
+
+/// The strategy that `options` asks the server's bots to play.
+fn stand_in(options: &Options) -> Result<Box<dyn Strategy>, String> {
+    strategy::by_name(&options.strategy)
+        .ok_or_else(|| format!("unknown strategy {}", options.strategy))
+}
 
-    let mut streams: Vec<TcpStream> = vec![];
-    for stream in listener.incoming() {
-        streams.push(stream.unwrap());
-        let len = streams.len();
-        if len < 4 {
-            println!("Waiting for {} player(s).", 4-len);
-        } else {
-            println!("Game!");
-            break;
+/// Hands the player on `stream` back the seat that `token` was issued
+/// for.
+fn rejoin(lobby: &Lobby, token: &str, stream: TcpStream)
+          -> Result<(), String> {
+    let tables = lobby.lock().unwrap();
+    for table in tables.iter() {
+        if let Some(i) = table.tokens.iter().position(|t| t == token) {
+            return table.sender.send(Message::Back(i+1, stream))
+                .map_err(|_| format!("table {} is over", table.name))
         }
     }
-    run(streams);
+    Err("unknown token".into())
+}
+
+/// The checkpoint of table `name`, next to `path`: `match.txt` becomes
+/// `match-name.txt`.
+fn table_file(path: &Path, name: &str) -> PathBuf {
+    let mut file = path.file_stem().unwrap_or_default().to_os_string();
+    file.push(format!("-{}", name));
+    if let Some(ext) = path.extension() {
+        file.push(".");
+        file.push(ext);
+    }
+    path.with_file_name(file)
 }
 
-fn run(mut streams: Vec<TcpStream>) {
-    let mut bot = dealer::new();
-    let mut player_input = None;
-    loop {
-        let mut inp = String::new();
-        if let Some(p) = player_input {
-            inp = utils::read_line(&streams[p-1]);
+/// Passes every line from `stream` on to the match as coming from seat
+/// `p` over connection `conn`, until it is closed.
+fn listen(p: game::PlayerNum, conn: usize, stream: TcpStream,
+          sender: Sender<Message>) {
+    thread::spawn(move || {
+        while let Some(line) = utils::try_read_line(&stream) {
+            if sender.send(Message::Line(p, conn, line)).is_err() {
+                return
+            }
         }
-        let (outputs, player_input_, stop) = bot.actuate(&inp);
-        player_input = player_input_;
-        for output in outputs {
-            for sout in stream_outputs(output) {
-                streams[sout.0 - 1].write((sout.1 + "\r\n").as_bytes())
-                    .expect("write error");
+        sender.send(Message::Gone(p, conn)).ok();
+    });
+}
+
+impl Host {
+    /// Seats the next player, who can come back with `token`, or one of
+    /// the server's bots if there is no `stream`. `stand_in` plays for
+    /// them while they are away, or all along for a bot.
+    fn sit(&mut self, stream: Option<TcpStream>, token: String,
+           stand_in: Box<dyn Strategy>) {
+        let p = self.seats.len() + 1;
+        let name = match stream {
+            Some(ref stream) => {
+                if let Ok(reader) = stream.try_clone() {
+                    listen(p, 0, reader, self.sender.clone());
+                }
+                stream.peer_addr().map(|a| a.to_string())
+                    .unwrap_or_else(|_| "?".into())
+            }
+            None => format!("{} bot", self.strategy),
+        };
+        self.seats.push(Seat {
+            stream,
+            conn: 0,
+            greeted: false,
+            rejected: false,
+            pending: VecDeque::new(),
+            stand_in,
+            name,
+        });
+        self.tokens.push(token);
+    }
+
+    fn run(mut self) {
+        let names: Vec<_> = self.seats.iter().map(|s| s.name.clone())
+            .collect();
+        let mut player_input = None;
+        loop {
+            let next = match player_input {
+                Some(p) => self.next_move(p),
+                None => Move::Line(String::new()),
+            };
+            let (outputs, player_input_, stop) = match next {
+                Move::Line(line) => self.dealer.actuate(&line),
+                Move::Cards(cards) => self.dealer.play(cards),
+            };
+            player_input = player_input_;
+            if let Some(ref path) = self.checkpoint {
+                let over = outputs.iter()
+                    .any(|o| matches!(*o, Output::MatchEnd(_)));
+                if over {
+                    fs::remove_file(path).ok();
+                } else if let Some(text) = self.dealer.save() {
+                    if let Err(e) = fs::write(path, text) {
+                        println!("Cannot write the checkpoint {}: {}",
+                                 path.display(), e);
+                    }
+                }
+            }
+            if let Some(ref dir) = self.records {
+                let ended = outputs.iter().any(|o| matches!(*o, Output::End(_)));
+                if ended {
+                    let game = self.dealer.finished().unwrap().clone();
+                    let record = Record::new(game, names.clone());
+                    match write_record(dir, &record) {
+                        Ok(path) => println!("Recorded the round in {}.",
+                                             path.display()),
+                        Err(e) => println!("Cannot write the record: {}", e),
+                    }
+                }
+            }
+            for output in outputs {
+                match output {
+                    Output::You(p) => self.seats[p-1].greeted = true,
+                    Output::Play(p, _) => self.seats[p-1].rejected = false,
+                    Output::PlayError(p, _) => self.seats[p-1].rejected = true,
+                    _ => (),
+                }
+                for (p, msg) in stream_outputs(output, &self.tokens) {
+                    self.send(p, &msg);
+                }
+            }
+            if stop {
+                break;
             }
         }
-        if stop {
-            break;
+        // Lets the listening threads go.
+        for seat in &self.seats {
+            if let Some(ref stream) = seat.stream {
+                stream.shutdown(Shutdown::Both).ok();
+            }
+        }
+    }
+
+    /// Waits for the next line from `p`, keeping track of everyone else
+    /// meanwhile. The seat's stand-in answers while `p` is away.
+    fn next_move(&mut self, p: game::PlayerNum) -> Move {
+        loop {
+            while let Some(line) = self.seats[p-1].pending.pop_front() {
+                // Players who come back mid-round say they're ready again.
+                if self.dealer.game().is_some() && line.trim() == "G" {
+                    continue
+                }
+                return Move::Line(line)
+            }
+            let seat = &mut self.seats[p-1];
+            if seat.stream.is_none() {
+                return match self.dealer.game() {
+                    Some(game) => {
+                        let view = game.view(p);
+                        let cards = match view.fallback_move() {
+                            Some(cards) if seat.rejected => cards,
+                            _ => seat.stand_in.play(&view),
+                        };
+                        Move::Cards(cards)
+                    }
+                    None => Move::Line("G".into()),
+                }
+            }
+            // The host holds a sender itself, so this never fails.
+            match self.receiver.recv().unwrap() {
+                Message::Line(q, conn, line) => {
+                    if conn == self.seats[q-1].conn {
+                        self.seats[q-1].pending.push_back(line);
+                    }
+                }
+                Message::Gone(q, conn) => {
+                    if conn == self.seats[q-1].conn {
+                        self.leave(q);
+                    }
+                }
+                Message::Back(q, stream) => self.come_back(q, stream),
+            }
+        }
+    }
+
+    /// Writes `msg` to `p`, if they are here.
+    fn send(&mut self, p: game::PlayerNum, msg: &str) {
+        let failed = match self.seats[p-1].stream {
+            Some(ref mut stream) => {
+                stream.write_all(format!("{}\r\n", msg).as_bytes()).is_err()
+            }
+            None => false,
+        };
+        if failed {
+            self.leave(p);
+        }
+    }
+
+    fn send_all(&mut self, msg: &str) {
+        for p in 1..self.seats.len()+1 {
+            self.send(p, msg);
+        }
+    }
+
+    /// Notes that `p` lost their connection.
+    fn leave(&mut self, p: game::PlayerNum) {
+        if let Some(stream) = self.seats[p-1].stream.take() {
+            stream.shutdown(Shutdown::Both).ok();
+            println!("Table {}: #{} left, a bot plays for them.", self.name, p);
+            let msg = format!("! #{} left the table, a bot plays for them.", p);
+            self.send_all(&msg);
+        }
+    }
+
+    /// Gives `p` their seat back and tells them where the round is at.
+    fn come_back(&mut self, p: game::PlayerNum, stream: TcpStream) {
+        self.leave(p);
+        let seat = &mut self.seats[p-1];
+        seat.conn += 1;
+        seat.pending.clear();
+        if let Ok(reader) = stream.try_clone() {
+            listen(p, seat.conn, reader, self.sender.clone());
+        }
+        seat.stream = Some(stream);
+        println!("Table {}: #{} is back.", self.name, p);
+        self.send_all(&format!("! #{} is back.", p));
+        // Otherwise the dealer tells them their number when it gets to it.
+        if !self.seats[p-1].greeted {
+            return
+        }
+        self.send(p, &format!("U #{} {}", p, self.tokens[p-1]));
+        for output in self.dealer.catch_up() {
+            for (q, msg) in stream_outputs(output, &self.tokens) {
+                if q == p {
+                    self.send(p, &msg);
+                }
+            }
         }
     }
 }
 
-fn stream_outputs(out: Output) -> Vec<(game::PlayerNum, String)> {
+/// Writes `record` to a new file in `dir`, named after its date and seed.
+fn write_record(dir: &Path, record: &Record) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let name = format!("{}-{}", record.date, record.game.seed().unwrap_or(0));
+    let mut path = dir.join(format!("{}.txt", name));
+    let mut n = 1;
+    while path.exists() {
+        n += 1;
+        path = dir.join(format!("{}-{}.txt", name, n));
+    }
+    fs::write(&path, record.to_string())?;
+    Ok(path)
+}
+
+/// The lines for `out`, and who they go to. `tokens` has every player's
+/// reconnect token.
+fn stream_outputs(out: Output, tokens: &[String])
+                  -> Vec<(game::PlayerNum, String)> {
+    let players = tokens.len();
     match out {
         Output::You(p) => {
-            vec![(p, format!("U #{}", p))]
+            vec![(p, format!("U #{} {}", p, tokens[p-1]))]
+        }
+        Output::Rules(ref rules) => {
+            out_to_all(players, format!("R {}", rules))
         }
         Output::Error(ref msg) => {
-            out_to_all(format!("! #{}", msg))
+            out_to_all(players, format!("! #{}", msg))
         }
         Output::Deal(p, ref cards) => {
             let str_cards: Vec<_> = cards.iter().map(|c| c.to_string()).collect();
             vec![(p, format!("D {}", str_cards.join(" ")))]
         }
         Output::Turn(ref t) => {
-            out_to_all(format!("T #{} {}", t.player(), match *t {
+            out_to_all(players, format!("T #{} {}", t.player(), match *t {
                 game::Turn::Start(_) => 'S',
                 game::Turn::Follow(_) => 'F',
                 game::Turn::Any(_) => 'A',
@@ -85,27 +645,43 @@ fn stream_outputs(out: Output) -> Vec<(game::PlayerNum, String)> {
             }))
         }
         Output::Play(p, ref cards) => {
-            out_to_all(format!("P #{} {}", p, cards))
+            out_to_all(players, format!("P #{} {}", p, cards))
         }
         Output::PlayError(p, e) => {
-            let mut outs = out_to_all(format!("! #{} didn't play properly.", p));
+            let msg = format!("! #{} didn't play properly.", p);
+            let mut outs = out_to_all(players, msg);
             outs.push((p, format!("? {}", e)));
             outs
         }
         Output::Win(p) => {
-            out_to_all(format!("W #{}", p))
+            out_to_all(players, format!("W #{}", p))
+        }
+        Output::Counts(ref counts) => {
+            let counts: Vec<_> = counts.iter().map(|n| n.to_string())
+                .collect();
+            out_to_all(players, format!("C {}", counts.join(" ")))
         }
         Output::End(ref winners) => {
             let winners: Vec<_> = winners.iter().map(|w| format!("#{}", w))
                 .collect();
-            out_to_all(format!("E {}", winners.join(" ")))
+            out_to_all(players, format!("E {}", winners.join(" ")))
+        }
+        Output::Score(round, ref scores) => {
+            let scores: Vec<_> = scores.iter().map(|s| s.to_string())
+                .collect();
+            out_to_all(players, format!("S {} {}", round, scores.join(" ")))
+        }
+        Output::MatchEnd(ref ranking) => {
+            let ranking: Vec<_> = ranking.iter().map(|p| format!("#{}", p))
+                .collect();
+            out_to_all(players, format!("M {}", ranking.join(" ")))
         }
     }
 }
 
-fn out_to_all(s: String) -> Vec<(game::PlayerNum, String)> {
+fn out_to_all(players: usize, s: String) -> Vec<(game::PlayerNum, String)> {
     let mut res = vec![];
-    for p in 1..5 {
+    for p in 1..players+1 {
         res.push((p, s.clone()));
     }
     res
@@ -0,0 +1,13 @@
+//! Always plays the lowest combination it can.
+
+use game::{Cards, PlayerView};
+use super::Strategy;
+
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    /// The lowest play possible, or a pass if there is none.
+    fn play(&mut self, view: &PlayerView) -> Cards {
+        view.legal_moves()[0].clone()
+    }
+}
@@ -0,0 +1,208 @@
+//! Information set Monte Carlo tree search.
+//!
+//! Every iteration deals the unseen cards at random to the opponents, in
+//! the numbers they are known to hold, and replays the game so far on top
+//! of that deal. The tree of moves is then walked down as far as it goes
+//! for that deal, grown by one move, and the rest of the round is played
+//! out at random. Moves are shared between deals, so the tree ends up with
+//! the moves that work best over all the hands the opponents could hold.
+
+use std::time::{Duration, Instant};
+
+use rand::{self, Rng, SeedableRng, Isaac64Rng};
+
+use game::{Cards, Hand, PlayerNum, PlayerView};
+use super::Strategy;
+
+/// How much to explore moves that haven't been tried much.
+const EXPLORATION: f64 = 0.7;
+
+/// When to stop searching and play.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+struct Node {
+    player: PlayerNum,
+    /// The move that leads here.
+    cards: Cards,
+    bits: Hand,
+    children: Vec<usize>,
+    visits: f64,
+    reward: f64,
+    /// How many times the move could have been chosen.
+    avail: f64,
+}
+
+impl Node {
+    fn new(player: PlayerNum, cards: Cards) -> Node {
+        Node {
+            player,
+            bits: Hand::from(&cards),
+            cards,
+            children: vec![],
+            visits: 0.0,
+            reward: 0.0,
+            avail: 1.0,
+        }
+    }
+
+    fn ucb(&self) -> f64 {
+        self.reward / self.visits
+            + EXPLORATION * (self.avail.ln() / self.visits).sqrt()
+    }
+}
+
+pub struct Ismcts {
+    rng: Isaac64Rng,
+    budget: Budget,
+}
+
+impl Ismcts {
+    pub fn new(seed: u64, budget: Budget) -> Ismcts {
+        Ismcts {
+            rng: Isaac64Rng::from_seed(&[seed]),
+            budget,
+        }
+    }
+
+    /// Reads `seed=N`, `iterations=N` and `time=MS` options. Unless given,
+    /// the seed is random and the budget is 1000 iterations.
+    pub fn with_options(options: &[&str]) -> Option<Ismcts> {
+        let mut seed = rand::thread_rng().gen();
+        let mut budget = Budget::Iterations(1000);
+        for option in options {
+            let kv: Vec<_> = option.splitn(2, '=').collect();
+            let n = kv.get(1).and_then(|v| v.parse().ok())?;
+            match kv[0] {
+                "seed" => seed = n,
+                "iterations" => budget = Budget::Iterations(n as usize),
+                "time" => budget = Budget::Time(Duration::from_millis(n)),
+                _ => return None,
+            }
+        }
+        Some(Ismcts::new(seed, budget))
+    }
+
+    fn iterate(&mut self, view: &PlayerView, tree: &mut Vec<Node>) {
+        let mut game = match view.determinize(&mut self.rng) {
+            Some(game) => game,
+            None => return,
+        };
+        let mut path = vec![0];
+        let mut node = 0;
+        // Walk down the tree, and add a move when there is one to add.
+        while game.winners().is_empty() {
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            let bits: Vec<_> = moves.iter().map(Hand::from).collect();
+            let children: Vec<_> = tree[node].children.iter().cloned()
+                .filter(|&c| {
+                    tree[c].player == p && bits.contains(&tree[c].bits)
+                })
+                .collect();
+            for &c in &children {
+                tree[c].avail += 1.0;
+            }
+            let untried: Vec<_> = moves.iter().zip(&bits)
+                .filter(|&(_, b)| children.iter().all(|&c| tree[c].bits != *b))
+                .map(|(m, _)| m)
+                .collect();
+            if !untried.is_empty() {
+                let i = self.rng.gen_range(0, untried.len());
+                let cards = untried[i].clone();
+                game.play(&cards).unwrap();
+                tree.push(Node::new(p, cards));
+                let child = tree.len() - 1;
+                tree[node].children.push(child);
+                path.push(child);
+                break
+            }
+            node = children.into_iter()
+                .max_by(|&a, &b| {
+                    tree[a].ucb().partial_cmp(&tree[b].ucb()).unwrap()
+                })
+                .unwrap();
+            game.play(&tree[node].cards).unwrap();
+            path.push(node);
+        }
+        // Play out the rest of the round at random.
+        while game.winners().is_empty() {
+            let p = game.turn().player();
+            let moves = game.legal_moves(p);
+            let cards = &moves[self.rng.gen_range(0, moves.len())];
+            game.play(cards).unwrap();
+        }
+        let counts = game.card_counts();
+        for &i in &path[1..] {
+            let node = &mut tree[i];
+            node.visits += 1.0;
+            node.reward += reward(&counts, node.player, view);
+        }
+    }
+}
+
+/// 1 for going out first, otherwise a little for every card gotten rid of.
+fn reward(counts: &[usize], p: PlayerNum, view: &PlayerView) -> f64 {
+    match counts[p-1] {
+        0 => 1.0,
+        n => {
+            let size = view.rules.hand_size() as f64;
+            0.3 * (1.0 - n as f64 / size).max(0.0)
+        }
+    }
+}
+
+impl Strategy for Ismcts {
+    fn play(&mut self, view: &PlayerView) -> Cards {
+        let moves = view.legal_moves();
+        if moves.len() == 1 {
+            return moves[0].clone()
+        }
+        let mut tree = vec![Node::new(0, Cards::pass())];
+        let start = Instant::now();
+        let mut iterations = 0;
+        loop {
+            match self.budget {
+                Budget::Iterations(n) if iterations >= n => break,
+                Budget::Time(t) if start.elapsed() >= t => break,
+                _ => (),
+            }
+            self.iterate(view, &mut tree);
+            iterations += 1;
+        }
+        // The most visited move, or the lowest if nothing could be tried.
+        let best = tree[0].children.iter().max_by(|&&a, &&b| {
+            tree[a].visits.partial_cmp(&tree[b].visits).unwrap()
+        });
+        match best {
+            Some(&i) => tree[i].cards.clone(),
+            None => moves[0].clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{Game, RuleSet};
+
+    #[test]
+    fn reproducible() {
+        let mut game = Game::with_seed(RuleSet::default(), 4);
+        for _ in 0..6 {
+            let p = game.turn().player();
+            let cards = game.legal_moves(p)[0].clone();
+            game.play(&cards).unwrap();
+        }
+        let p = game.turn().player();
+        let view = game.view(p);
+        let plays: Vec<_> = (0..2).map(|_| {
+            Ismcts::new(3, Budget::Iterations(100)).play(&view)
+        }).collect();
+        assert!(game.legal_moves(p).contains(&plays[0]));
+        assert_eq!(plays[0].cards(), plays[1].cards());
+    }
+}
@@ -0,0 +1,58 @@
+//! Bots that decide what to play.
+//!
+//! A `Strategy` only ever sees a `PlayerView`, so the same strategy can
+//! play over the network through `client::bot_with` or against the engine
+//! directly through `Game::view`.
+
+use game::{Cards, PlayerView};
+
+pub mod greedy;
+pub mod planner;
+pub mod ismcts;
+
+pub trait Strategy: Send {
+    /// What to play next. Only called on the player's turn, and has to
+    /// return one of `view.legal_moves()`.
+    fn play(&mut self, view: &PlayerView) -> Cards;
+}
+
+/// The names `by_name` knows about.
+pub const NAMES: &[&str] = &["greedy", "planner", "ismcts"];
+
+/// The strategy called `spec`, if there is one. Options can follow the name
+/// after commas, e.g. `ismcts,seed=7,iterations=500`.
+pub fn by_name(spec: &str) -> Option<Box<dyn Strategy>> {
+    let mut parts = spec.split(',');
+    let name = parts.next().unwrap();
+    let options: Vec<_> = parts.collect();
+    match name {
+        "greedy" if options.is_empty() => Some(Box::new(greedy::Greedy)),
+        "planner" if options.is_empty() => Some(Box::new(planner::Planner)),
+        "ismcts" => {
+            let ismcts = ismcts::Ismcts::with_options(&options)?;
+            Some(Box::new(ismcts))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::{Game, RuleSet, Turn};
+
+    #[test]
+    fn strategies_play_legally() {
+        for name in &["greedy", "planner", "ismcts,seed=1,iterations=30"] {
+            let mut bots: Vec<_> = (0..4).map(|_| by_name(name).unwrap())
+                .collect();
+            let mut game = Game::with_seed(RuleSet::default(), 1);
+            while game.turn() != Turn::End {
+                let p = game.turn().player();
+                let cards = bots[p-1].play(&game.view(p));
+                assert!(game.legal_moves(p).contains(&cards), "{}", name);
+                game.play(&cards).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Plans how to empty the hand in as few plays as possible.
+//!
+//! The hand is split into combinations, and the bot sticks to that plan:
+//! it leads with its lowest planned combination and only follows with
+//! planned ones. Control cards (2s, four of a kinds and straight flushes)
+//! are saved for taking back the lead, unless the bot is about to go out or
+//! an opponent is down to their last cards.
+
+use std::collections::HashMap;
+
+use game::{hand, Card, Cards, Hand, Kind, PlayerView, RuleSet, Turn};
+use super::Strategy;
+
+/// How many hands `partition` may look at before falling back to grouping
+/// the rest of the cards by rank.
+const BUDGET: usize = 5000;
+
+/// How many cards an opponent has left when the bot stops holding back.
+const DANGER: usize = 4;
+
+pub struct Planner;
+
+/// Splits `hand` into combinations, as few as possible and then with as few
+/// and as high singles as possible.
+pub fn partition(hand: Hand, rules: &RuleSet) -> Vec<Cards> {
+    let mut memo = HashMap::new();
+    plan(hand, rules, &mut memo)
+}
+
+/// Fewer plays first, then fewer singles, then higher singles.
+fn cost(plan: &[Cards], rules: &RuleSet) -> (usize, usize, usize) {
+    let singles: Vec<_> = plan.iter().filter(|c| c.len() == 1).collect();
+    let low = singles.iter().map(|c| 52 - rules.card_value(c[0])).sum();
+    (plan.len(), singles.len(), low)
+}
+
+fn plan(hand: Hand, rules: &RuleSet, memo: &mut HashMap<Hand, Vec<Cards>>)
+        -> Vec<Cards> {
+    if hand.is_empty() {
+        return vec![]
+    }
+    if let Some(plan) = memo.get(&hand) {
+        return plan.clone()
+    }
+    if memo.len() >= BUDGET {
+        return by_rank(hand, rules)
+    }
+    // Every plan has to put the lowest card somewhere, so only the
+    // combinations with it need to be tried.
+    let lowest = lowest_card(hand, rules);
+    let mut best: Option<Vec<Cards>> = None;
+    for cards in hand::combinations(hand, rules, None) {
+        if !cards.contains(&lowest) {
+            continue
+        }
+        let mut rest = plan(hand - Hand::from(&cards), rules, memo);
+        rest.push(cards);
+        if best.as_ref().map_or(true, |b| cost(&rest, rules) < cost(b, rules)) {
+            best = Some(rest);
+        }
+    }
+    let best = best.unwrap();
+    memo.insert(hand, best.clone());
+    best
+}
+
+fn lowest_card(hand: Hand, rules: &RuleSet) -> Card {
+    hand.cards().into_iter().min_by_key(|&c| rules.card_value(c)).unwrap()
+}
+
+/// Pairs and trios of the same rank, and singles for the rest.
+fn by_rank(hand: Hand, rules: &RuleSet) -> Vec<Cards> {
+    let mut cards = hand.cards();
+    rules.sort(&mut cards);
+    let mut plan = vec![];
+    for group in cards.chunk_by(|a, b| a.rank == b.rank) {
+        let size = if group.len() == 4 { 2 } else { group.len() };
+        for chunk in group.chunks(size) {
+            plan.push(Cards::with_rules(chunk.to_vec(), rules).unwrap());
+        }
+    }
+    plan
+}
+
+/// Whether `cards` are better kept for taking back the lead.
+fn is_control(cards: &Cards) -> bool {
+    match cards.kind() {
+        Some(Kind::FourOfAKind(_)) | Some(Kind::StraightFlush(_)) => true,
+        Some(kind) => kind.card().rank == '2',
+        None => false,
+    }
+}
+
+fn same(c1: &Cards, c2: &Cards) -> bool {
+    Hand::from(c1) == Hand::from(c2)
+}
+
+impl Planner {
+    fn lead(&self, view: &PlayerView, plan: Vec<Cards>, low: usize)
+            -> Cards {
+        let value = |c: &Cards| {
+            view.rules.card_value(c.kind().unwrap().card())
+        };
+        let (control, mut rest): (Vec<_>, Vec<_>) =
+            plan.into_iter().partition(is_control);
+        if rest.len() <= 1 && !control.is_empty() {
+            // Keep the lead with a control card, then go out.
+            return control[0].clone()
+        }
+        // Get rid of as many low cards as possible.
+        rest.sort_by_key(|c| (5 - c.len(), value(c)));
+        if low == 1 {
+            // Don't give the last card away with a single.
+            if let Some(cards) = rest.iter().find(|c| c.len() > 1) {
+                return cards.clone()
+            }
+            return rest.pop().unwrap()
+        }
+        rest.remove(0)
+    }
+
+    fn follow(&self, view: &PlayerView, plan: Vec<Cards>, moves: Vec<Cards>,
+              low: usize) -> Cards {
+        let hand: Hand = view.hand.iter().cloned().collect();
+        let plays: Vec<_> = moves.into_iter().filter(|c| !c.is_pass())
+            .collect();
+        let free = low <= DANGER || plan.len() <= 2;
+        let planned = plays.iter().find(|c| {
+            plan.iter().any(|p| same(p, c)) && (free || !is_control(c))
+        });
+        if let Some(cards) = planned {
+            return cards.clone()
+        }
+        if low <= DANGER {
+            if let Some(cards) = plays.last() {
+                return cards.clone()
+            }
+        }
+        // Break up the plan only if it doesn't take any more plays.
+        for cards in plays.iter().filter(|c| !is_control(c)).take(8) {
+            let rest = partition(hand - Hand::from(cards), &view.rules);
+            if rest.len() <= plan.len() {
+                return cards.clone()
+            }
+        }
+        Cards::pass()
+    }
+}
+
+impl Strategy for Planner {
+    fn play(&mut self, view: &PlayerView) -> Cards {
+        let moves = view.legal_moves();
+        let out = moves.iter().find(|c| c.len() == view.hand.len());
+        if let Some(cards) = out {
+            return cards.clone()
+        }
+        let hand: Hand = view.hand.iter().cloned().collect();
+        let plan = partition(hand, &view.rules);
+        // The fewest cards any opponent still holds.
+        let low = view.card_counts.iter().enumerate()
+            .filter(|&(i, &n)| i+1 != view.player && n > 0)
+            .map(|(_, &n)| n)
+            .min()
+            .unwrap_or(0);
+        match view.turn.unwrap() {
+            Turn::Start(_) => {
+                plan.into_iter().find(|c| c.contains(&view.hand[0]))
+                    .unwrap()
+            }
+            Turn::Any(_) => self.lead(view, plan, low),
+            _ => self.follow(view, plan, moves, low),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hand(s: &str) -> Hand {
+        s.split_whitespace().map(|c| c.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn fewest_plays() {
+        let rules = RuleSet::default();
+        let plan = partition(hand("3C 4D 5H 6S 7C 9C 9D KS KH KD 2S"), &rules);
+        let strs: Vec<_> = plan.iter().map(|c| c.to_string()).collect();
+        assert_eq!(vec!["2S", "9C 9D KS KH KD", "3C 4D 5H 6S 7C"], strs);
+    }
+
+    #[test]
+    fn saves_control_cards() {
+        let rules = RuleSet::default();
+        let mut view = PlayerView::new(1, rules, "4C 4S 8D 2C 2H".split(' ')
+            .map(|c| c.parse().unwrap()).collect());
+        view.set_turn(Turn::Follow(1));
+        view.add_play(2, "9D".parse().unwrap()).unwrap();
+        assert!(Planner.play(&view).is_pass());
+        // Unless someone is about to go out.
+        view.card_counts[1] = 1;
+        assert_eq!("2H", Planner.play(&view).to_string());
+    }
+}
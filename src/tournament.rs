@@ -0,0 +1,298 @@
+//! Self-play between bot strategies, without a server.
+//!
+//! Every game is a single round dealt from its own seed, with the
+//! strategies taking turns in each seat. Games are shared out between
+//! threads and the results are summed up per strategy.
+//!
+//! In duplicate mode every deal is played once for each way of seating the
+//! strategies, so that no strategy is luckier with the cards than another.
+//! Results are then compared deal by deal, which tells strategies apart in
+//! far fewer deals.
+
+use std::fmt;
+use std::thread;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use game::{Game, PlayerNum, RuleSet, Turn};
+use strategy;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Strategy names as taken by `strategy::by_name`, one for each seat.
+    /// Repeated from the start if there are fewer than players.
+    pub strategies: Vec<String>,
+    pub games: usize,
+    pub threads: usize,
+    /// Game `i` is dealt from `seed + i`.
+    pub seed: u64,
+    pub rules: RuleSet,
+    /// Whether to play every deal with every seating, in which case `games`
+    /// is the number of deals.
+    pub duplicate: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            strategies: vec!["greedy".into()],
+            games: 1000,
+            threads: 4,
+            seed: 0,
+            rules: RuleSet::default(),
+            duplicate: false,
+        }
+    }
+}
+
+/// How one seat did in a game.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Finish {
+    /// From 1, where the last player left with cards is last.
+    pub position: usize,
+    /// Cards held when the first player went out.
+    pub cards: usize,
+}
+
+/// The results of a strategy over all the games it played, counting a game
+/// once for every seat it had.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub name: String,
+    pub deals: usize,
+    pub games: usize,
+    pub wins: usize,
+    pub positions: usize,
+    pub cards: usize,
+    /// The win rate on each deal, summed, and squared and summed.
+    deal_rates: (f64, f64),
+}
+
+impl Stats {
+    fn new(name: &str) -> Stats {
+        Stats {
+            name: name.into(),
+            deals: 0,
+            games: 0,
+            wins: 0,
+            positions: 0,
+            cards: 0,
+            deal_rates: (0.0, 0.0),
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.games as f64
+    }
+
+    /// Half the width of the 95% confidence interval of the win rate, from
+    /// how much it varies between deals.
+    pub fn win_rate_error(&self) -> f64 {
+        let n = self.deals as f64;
+        let mean = self.deal_rates.0 / n;
+        let variance = (self.deal_rates.1 / n - mean*mean).max(0.0);
+        1.96 * (variance / n).sqrt()
+    }
+
+    pub fn average_position(&self) -> f64 {
+        self.positions as f64 / self.games as f64
+    }
+
+    pub fn average_cards(&self) -> f64 {
+        self.cards as f64 / self.games as f64
+    }
+}
+
+/// The strategies from best to worst win rate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report(pub Vec<Stats>);
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<4} {:<24} {:>6} {:>7} {:>7} {:>16} {:>8} {:>9}",
+                 "rank", "strategy", "deals", "games", "wins", "win rate",
+                 "avg pos", "avg cards")?;
+        for (i, s) in self.0.iter().enumerate() {
+            let rate = format!("{:.1}% ± {:.1}%",
+                               s.win_rate()*100.0, s.win_rate_error()*100.0);
+            writeln!(f, "{:<4} {:<24} {:>6} {:>7} {:>7} {:>16} {:>8.2} {:>9.2}",
+                     i+1, s.name, s.deals, s.games, s.wins, rate,
+                     s.average_position(), s.average_cards())?;
+        }
+        Ok(())
+    }
+}
+
+/// Plays one round dealt from `seed`, with `names[p-1]` in seat `p`, and
+/// returns how each seat finished.
+pub fn play_game(rules: &RuleSet, seed: u64, names: &[&str]) -> Vec<Finish> {
+    let mut bots: Vec<_> = names.iter()
+        .map(|name| strategy::by_name(name).expect("unknown strategy"))
+        .collect();
+    let mut game = Game::with_seed(rules.clone(), seed);
+    let mut counts = None;
+    loop {
+        let p = match game.turn() {
+            Turn::End => break,
+            t => t.player(),
+        };
+        let cards = bots[p-1].play(&game.view(p));
+        let wins = game.play(&cards).expect("strategy played illegally");
+        if wins && counts.is_none() {
+            counts = Some(game.card_counts());
+        }
+    }
+    let counts = counts.unwrap();
+    let mut order: Vec<PlayerNum> = game.winners();
+    let last = (1..names.len()+1).find(|p| !order.contains(p)).unwrap();
+    order.push(last);
+    (1..names.len()+1).map(|p| Finish {
+        position: order.iter().position(|&q| q == p).unwrap() + 1,
+        cards: counts[p-1],
+    }).collect()
+}
+
+/// Every distinct way of seating `names`.
+fn seatings<'a>(names: &[&'a str]) -> Vec<Vec<&'a str>> {
+    if names.len() <= 1 {
+        return vec![names.to_vec()]
+    }
+    let mut res: Vec<Vec<&str>> = vec![];
+    for i in 0..names.len() {
+        let mut rest = names.to_vec();
+        let first = rest.remove(i);
+        for mut seating in seatings(&rest) {
+            seating.insert(0, first);
+            if !res.contains(&seating) {
+                res.push(seating);
+            }
+        }
+    }
+    res
+}
+
+/// Adds up the games played on one deal, each given as the strategies in
+/// their seats and how they finished.
+fn tally(stats: &mut Vec<Stats>, games: &[(Vec<&str>, Vec<Finish>)]) {
+    let mut deal: Vec<(&str, usize, usize)> = vec![];
+    for (names, finishes) in games {
+        for (&name, finish) in names.iter().zip(finishes) {
+            let i = match stats.iter().position(|s| s.name == name) {
+                Some(i) => i,
+                None => {
+                    stats.push(Stats::new(name));
+                    stats.len() - 1
+                }
+            };
+            let won = finish.position == 1;
+            let s = &mut stats[i];
+            s.games += 1;
+            s.wins += won as usize;
+            s.positions += finish.position;
+            s.cards += finish.cards;
+            match deal.iter_mut().find(|d| d.0 == name) {
+                Some(d) => {
+                    d.1 += 1;
+                    d.2 += won as usize;
+                }
+                None => deal.push((name, 1, won as usize)),
+            }
+        }
+    }
+    for (name, games, wins) in deal {
+        let s = stats.iter_mut().find(|s| s.name == name).unwrap();
+        let rate = wins as f64 / games as f64;
+        s.deals += 1;
+        s.deal_rates.0 += rate;
+        s.deal_rates.1 += rate*rate;
+    }
+}
+
+pub fn run(options: &Options) -> Report {
+    let players = options.rules.players;
+    let names: Vec<_> = options.strategies.iter().cycle().take(players)
+        .cloned()
+        .collect();
+    let next = Arc::new(AtomicUsize::new(0));
+    let stats = Arc::new(Mutex::new(vec![]));
+    let threads: Vec<_> = (0..options.threads.max(1)).map(|_| {
+        let (next, stats) = (next.clone(), stats.clone());
+        let (names, options) = (names.clone(), options.clone());
+        thread::spawn(move || loop {
+            let i = next.fetch_add(1, Ordering::SeqCst);
+            if i >= options.games {
+                break
+            }
+            let names: Vec<_> = names.iter().map(|n| &**n).collect();
+            let seed = options.seed.wrapping_add(i as u64);
+            let games: Vec<_> = if options.duplicate {
+                seatings(&names).into_iter().map(|seats| {
+                    let finishes = play_game(&options.rules, seed, &seats);
+                    (seats, finishes)
+                }).collect()
+            } else {
+                // Rotate the strategies around the table.
+                let seats: Vec<_> = (0..players)
+                    .map(|s| names[(s + i) % players])
+                    .collect();
+                let finishes = play_game(&options.rules, seed, &seats);
+                vec![(seats, finishes)]
+            };
+            tally(&mut stats.lock().unwrap(), &games);
+        })
+    }).collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    let mut stats = stats.lock().unwrap().clone();
+    stats.sort_by(|a, b| {
+        b.win_rate().partial_cmp(&a.win_rate()).unwrap()
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Report(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_seats() {
+        let options = Options {
+            strategies: vec!["greedy".into(), "planner".into()],
+            games: 8,
+            threads: 3,
+            ..Options::default()
+        };
+        let report = run(&options);
+        assert_eq!(2, report.0.len());
+        for stats in &report.0 {
+            assert_eq!(16, stats.games);
+        }
+        let wins: usize = report.0.iter().map(|s| s.wins).sum();
+        assert_eq!(8, wins);
+        assert_eq!(report, run(&options));
+    }
+
+    #[test]
+    fn duplicate() {
+        let names = ["a", "b", "b"];
+        assert_eq!(vec![vec!["a", "b", "b"], vec!["b", "a", "b"],
+                        vec!["b", "b", "a"]], seatings(&names));
+        let options = Options {
+            strategies: vec!["greedy".into(), "planner".into()],
+            games: 3,
+            threads: 2,
+            rules: "players=3".parse().unwrap(),
+            duplicate: true,
+            ..Options::default()
+        };
+        let report = run(&options);
+        // Greedy sits twice at a table of three, and planner once.
+        let greedy = report.0.iter().find(|s| s.name == "greedy").unwrap();
+        assert_eq!(3, greedy.deals);
+        assert_eq!(18, greedy.games);
+        let wins: usize = report.0.iter().map(|s| s.wins).sum();
+        assert_eq!(9, wins);
+    }
+}
@@ -0,0 +1,217 @@
+extern crate baraha;
+
+use baraha::game::{hand, Cards, DealError, Event, Game, GameError, RuleSet,
+                   Turn, LOWEST_CARD};
+
+#[test]
+fn first_play_must_include_lowest_card() {
+    let mut game = Game::new(RuleSet::default());
+    let first = match game.turn() {
+        Turn::Start(p) => p,
+        t => panic!("unexpected turn {:?}", t),
+    };
+    assert!(game.hand(first).contains(&LOWEST_CARD));
+    assert_eq!(13, game.hand(first).len());
+
+    assert_eq!(Err(GameError::CannotPass), game.play(&Cards::pass()));
+    let lowest = Cards::new(vec![LOWEST_CARD]).unwrap();
+    let other = game.hand(first).into_iter()
+        .find(|&c| c != LOWEST_CARD)
+        .unwrap();
+    assert_eq!(Err(GameError::MissingLowestCard),
+               game.play(&Cards::new(vec![other]).unwrap()));
+    assert_eq!(Ok(false), game.play(&lowest));
+    assert_eq!(12, game.hand(first).len());
+    assert_eq!(&[LOWEST_CARD], game.discard_pile());
+    assert_eq!(first, game.last_play().unwrap().0);
+    match game.turn() {
+        Turn::Follow(p) => assert_eq!(first % 4 + 1, p),
+        t => panic!("unexpected turn {:?}", t),
+    }
+}
+
+#[test]
+fn seeded_deals_are_reproducible() {
+    let rules = RuleSet::default();
+    let (g1, g2) = (Game::with_seed(rules.clone(), 42),
+                    Game::with_seed(rules.clone(), 42));
+    assert_eq!(Some(42), g1.seed());
+    for p in 1..5 {
+        assert_eq!(g1.hand(p), g2.hand(p));
+    }
+    let g3 = Game::with_seed(rules, 43);
+    assert!((1..5).any(|p| g3.hand(p) != g1.hand(p)));
+}
+
+#[test]
+fn explicit_deal() {
+    let deal = Game::with_seed(RuleSet::default(), 7);
+    let hands: Vec<_> = (1..5).map(|p| deal.hand(p)).collect();
+    let game = Game::from_hands(RuleSet::default(), hands.clone()).unwrap();
+    assert_eq!(None, game.seed());
+    assert_eq!(deal.turn().player(), game.turn().player());
+
+    let mut short = hands.clone();
+    short[1].pop();
+    assert_eq!(Err(DealError::HandSize(2)),
+               Game::from_hands(RuleSet::default(), short).map(|_| ()));
+
+    let mut dup = hands.clone();
+    let card = dup[0][0];
+    dup[1][0] = card;
+    assert_eq!(Err(DealError::Duplicate(card)),
+               Game::from_hands(RuleSet::default(), dup).map(|_| ()));
+
+    assert_eq!(Err(DealError::Players(3)),
+               Game::from_hands(RuleSet::default(), hands[..3].to_vec()).map(|_| ()));
+}
+
+#[test]
+fn house_rules() {
+    let rules: RuleSet = "suits=DCHS lead=hold-lowest".parse().unwrap();
+    let mut game = Game::with_seed(rules, 1);
+    let first = game.turn().player();
+    let lowest = "3D".parse().unwrap();
+    assert!(game.hand(first).contains(&lowest));
+    let highest = *game.hand(first).last().unwrap();
+    assert_eq!(Ok(false), game.play(&Cards::new(vec![highest]).unwrap()));
+}
+
+/// Plays singles only, passing whenever a single can't be played.
+fn play_out(game: &mut Game) {
+    loop {
+        let p = match game.turn() {
+            Turn::End => return,
+            t => t.player(),
+        };
+        let played = game.hand(p).into_iter()
+            .any(|c| game.play(&Cards::new(vec![c]).unwrap()).is_ok());
+        if !played {
+            game.play(&Cards::pass()).unwrap();
+        }
+    }
+}
+
+#[test]
+fn three_players() {
+    let rules: RuleSet = "players=3".parse().unwrap();
+    let mut game = Game::with_seed(rules, 5);
+    let sizes: Vec<_> = (1..4).map(|p| game.hand(p).len()).collect();
+    assert_eq!(52, sizes.iter().sum::<usize>());
+    let first = game.turn().player();
+    assert_eq!(18, sizes[first-1]);
+    assert!(game.hand(first).contains(&LOWEST_CARD));
+    play_out(&mut game);
+    assert_eq!(2, game.winners().len());
+}
+
+#[test]
+fn thirteen_card_deal() {
+    let rules: RuleSet = "players=2 deal=13".parse().unwrap();
+    for seed in 0..10 {
+        let mut game = Game::with_seed(rules.clone(), seed);
+        assert_eq!(2, game.players());
+        assert_eq!(13, game.hand(1).len());
+        assert_eq!(13, game.hand(2).len());
+        let first = game.turn().player();
+        assert_eq!(game.lowest_card(), game.hand(first)[0]);
+        play_out(&mut game);
+        assert_eq!(1, game.winners().len());
+    }
+}
+
+#[test]
+fn leader_plays_anything() {
+    let mut game = Game::with_seed(RuleSet::default(), 3);
+    let leader = game.turn().player() % 4 + 1;
+    game.set_leader(leader);
+    match game.turn() {
+        Turn::Any(p) => assert_eq!(leader, p),
+        t => panic!("unexpected turn {:?}", t),
+    }
+    let highest = *game.hand(leader).last().unwrap();
+    assert_eq!(Ok(false), game.play(&Cards::new(vec![highest]).unwrap()));
+    assert_eq!(vec![12, 13, 13, 13], {
+        let mut counts = game.card_counts();
+        counts.sort();
+        counts
+    });
+}
+
+/// Every legal move of `p`, found the slow way.
+fn subset_scan(game: &Game, p: usize) -> Vec<String> {
+    let mut moves: Vec<_> = hand::subset_scan(&game.hand(p), game.rules())
+        .into_iter()
+        .chain(Some(Cards::pass()))
+        .filter(|c| game.clone().play(c).is_ok())
+        .map(|c| c.to_string())
+        .collect();
+    moves.sort();
+    moves
+}
+
+#[test]
+fn legal_moves_match_subset_scan() {
+    for rules in &["", "wraparound=yes flush=rank", "twos=no lead=hold-lowest"] {
+        let rules: RuleSet = rules.parse().unwrap();
+        for seed in 0..5 {
+            let mut game = Game::with_seed(rules.clone(), seed);
+            for _ in 0..6 {
+                let p = game.turn().player();
+                let mut moves: Vec<_> = game.legal_moves(p).iter()
+                    .map(|c| c.to_string())
+                    .collect();
+                moves.sort();
+                assert_eq!(subset_scan(&game, p), moves);
+                assert!(game.legal_moves(p % 4 + 1).is_empty());
+                // Play the longest move to get to a variety of positions.
+                let m = game.legal_moves(p).into_iter()
+                    .max_by_key(|c| c.len())
+                    .unwrap();
+                game.play(&m).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn history_replay_and_undo() {
+    let mut game = Game::with_seed(RuleSet::default(), 11);
+    play_out(&mut game);
+    let history = game.history();
+    match history[0] {
+        Event::Deal(ref hands, turn) => {
+            assert_eq!(4, hands.len());
+            assert!(matches!(turn, Turn::Start(_)));
+        }
+        ref e => panic!("expected a deal, got {:?}", e),
+    }
+    let wins: Vec<_> = history.iter().filter_map(|e| match *e {
+        Event::Win(p) => Some(p),
+        _ => None,
+    }).collect();
+    assert_eq!(game.winners(), wins);
+    // Whoever starts a new trick made the last play before it.
+    for w in history.windows(2) {
+        if let (&Event::Play(p, _), &Event::NewTrick(q)) = (&w[0], &w[1]) {
+            assert!(p == q || wins.contains(&p));
+        }
+    }
+
+    let moves = game.moves();
+    let replayed = game.replay(moves.len());
+    assert_eq!(history, replayed.history());
+    let start = game.replay(0);
+    assert_eq!(1, start.history().len());
+    assert_eq!(Game::with_seed(RuleSet::default(), 11).card_counts(),
+               start.card_counts());
+
+    let mut undone = game.replay(10);
+    assert!(undone.undo());
+    assert_eq!(game.replay(9).history(), undone.history());
+    let p = moves[9].0;
+    assert_eq!(p, undone.turn().player());
+    undone.play(&moves[9].1).unwrap();
+    assert_eq!(game.replay(10).history(), undone.history());
+    assert!(!start.clone().undo());
+}
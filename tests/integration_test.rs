@@ -1,17 +1,222 @@
 extern crate baraha;
 
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process;
 use std::thread;
+use std::time::Duration;
 
-use baraha::{server, client};
+use baraha::{server, client, strategy};
+use baraha::client::Seat;
+use baraha::game::{Card, Cards, Game, Hand, RuleSet, Turn};
 
 #[test]
 fn host_and_play() {
+    let server = server::Server::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
     let mut joins = vec![];
-    for _ in 0..4 {
-        joins.push(thread::spawn(|| client::bot("localhost".into())));
+    for i in 0..8 {
+        let addr = addr.clone();
+        // Two tables playing at the same time.
+        let table = if i % 2 == 0 { "a" } else { "b" };
+        let seat = Seat::Table(table.into());
+        joins.push(thread::spawn(move || client::bot(addr, seat)));
     }
-    server::host();
     for join in joins {
         join.join().unwrap();
     }
+    // Tables leave the lobby once their match is over.
+    thread::sleep(Duration::from_millis(100));
+    assert!(client::tables(&addr).unwrap().is_empty());
+}
+
+#[test]
+fn list_tables() {
+    let server = server::Server::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    assert!(client::tables(&addr).unwrap().is_empty());
+    let bot = {
+        let addr = addr.clone();
+        thread::spawn(move || client::bot(addr, Seat::Table("main".into())))
+    };
+    thread::sleep(Duration::from_millis(100));
+    let tables = client::tables(&addr).unwrap();
+    assert_eq!(1, tables.len());
+    assert_eq!(("main", 1, 4),
+               (&*tables[0].name, tables[0].joined, tables[0].players));
+    for _ in 0..3 {
+        let addr = addr.clone();
+        thread::spawn(move || client::bot(addr, Seat::Table("main".into())));
+    }
+    bot.join().unwrap();
+}
+
+#[test]
+fn rejoin() {
+    let options = server::Options {
+        seed: Some(5),
+        bots: 3,
+        ..server::Options::default()
+    };
+    let server = server::Server::bind("127.0.0.1:0").unwrap()
+        .with_options(options);
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    let mut stream = TcpStream::connect(&*addr).unwrap();
+    stream.write_all(b"B nobody\r\n").unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    assert_eq!("? unknown token", lines.next().unwrap().unwrap());
+    stream.write_all(b"J main\r\n").unwrap();
+    assert_eq!("J main 4/4", lines.next().unwrap().unwrap());
+    let you = lines.next().unwrap().unwrap();
+    let token = you["U #4 ".len()..].to_string();
+    stream.write_all(b"G\r\n").unwrap();
+    // The server's bots play greedy, and the table waits for #4 to play.
+    let mut game = Game::with_seed(RuleSet::default(), 5);
+    let mut greedy = strategy::by_name("greedy").unwrap();
+    while game.turn().player() != 4 {
+        let cards = greedy.play(&game.view(game.turn().player()));
+        game.play(&cards).unwrap();
+    }
+    let view = game.view(4);
+    // Coming back over another connection.
+    let mut back = TcpStream::connect(&*addr).unwrap();
+    back.write_all(format!("B {}\r\n", token).as_bytes()).unwrap();
+    let mut lines = BufReader::new(back.try_clone().unwrap()).lines();
+    let mut told = vec![];
+    loop {
+        let line = lines.next().unwrap().unwrap();
+        let done = line.starts_with("T ");
+        told.push(line);
+        if done {
+            break
+        }
+    }
+    assert!(told.contains(&you));
+    let deal = told.iter().find(|l| l.starts_with("D ")).unwrap();
+    let mut hand: Hand = deal[2..].split_whitespace()
+        .map(|c| c.parse::<Card>().unwrap())
+        .collect();
+    for line in told.iter().filter(|l| l.starts_with("P #4 ")) {
+        for c in line["P #4 ".len()..].split_whitespace() {
+            hand.remove(c.parse().unwrap());
+        }
+    }
+    assert_eq!(view.hand.iter().cloned().collect::<Hand>(), hand);
+    let counts: Vec<_> = view.card_counts.iter().map(|n| n.to_string())
+        .collect();
+    let told_counts = told.iter().rev().find(|l| l.starts_with("C ")).unwrap();
+    assert_eq!(&format!("C {}", counts.join(" ")), told_counts);
+    let turn = match game.turn() {
+        Turn::Start(_) => "T #4 S",
+        Turn::Follow(_) => "T #4 F",
+        _ => "T #4 A",
+    };
+    assert_eq!(turn, told.last().unwrap());
+    let passes = told.iter().filter(|l| l.starts_with("P ") && l.len() <= 5)
+        .count();
+    assert_eq!(view.passed.len(), passes);
+    // A bot takes the seat back in turn and plays the match out.
+    drop(lines);
+    drop(back);
+    client::bot(addr, Seat::Back(token));
+}
+
+#[test]
+fn server_bots() {
+    let options = server::Options {
+        bots: 3,
+        ..server::Options::default()
+    };
+    let server = server::Server::bind("127.0.0.1:0").unwrap()
+        .with_options(options);
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    let mut stream = TcpStream::connect(&*addr).unwrap();
+    stream.write_all(b"J main\r\n").unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    // The bots take the first seats, so the table is full.
+    assert_eq!("J main 4/4", lines.next().unwrap().unwrap());
+    assert!(lines.next().unwrap().unwrap().starts_with("U #4 "));
+    drop(lines);
+    drop(stream);
+    // The match is played out without anyone connected.
+    for _ in 0..100 {
+        if client::tables(&addr).unwrap().is_empty() {
+            return
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    panic!("the bots never finished");
+}
+
+#[test]
+fn unknown_strategy() {
+    let options = server::Options {
+        bots: 1,
+        strategy: "nope".into(),
+        ..server::Options::default()
+    };
+    let server = server::Server::bind("127.0.0.1:0").unwrap()
+        .with_options(options);
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    let mut stream = TcpStream::connect(&*addr).unwrap();
+    stream.write_all(b"J main\r\n").unwrap();
+    let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+    assert_eq!("? unknown strategy nope", lines.next().unwrap().unwrap());
+    // The lobby is still there for everyone else.
+    assert!(client::tables(&addr).unwrap().is_empty());
+}
+
+#[test]
+fn wraparound_straight() {
+    let rules: RuleSet = "wraparound=yes".parse().unwrap();
+    // A deal in which greedy plays a straight that only wraps around.
+    let mut greedy = strategy::by_name("greedy").unwrap();
+    let wraps = |game: &Game| game.moves().iter()
+        .any(|(_, c)| Cards::new(c.cards().to_vec()).is_err());
+    let seed = (0..).find(|&seed| {
+        let mut game = Game::with_seed(rules.clone(), seed);
+        while game.turn() != Turn::End {
+            let cards = greedy.play(&game.view(game.turn().player()));
+            game.play(&cards).unwrap();
+        }
+        wraps(&game)
+    }).unwrap();
+    let options = server::Options {
+        seed: Some(seed),
+        rules,
+        bots: 3,
+        ..server::Options::default()
+    };
+    let server = server::Server::bind("127.0.0.1:0").unwrap()
+        .with_options(options);
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    client::bot(addr, Seat::Table("main".into()));
+}
+
+#[test]
+fn unwritable_checkpoint() {
+    let records = env::temp_dir()
+        .join(format!("baraha-records-{}", process::id()));
+    let options = server::Options {
+        checkpoint: Some("no/such/dir/match.txt".into()),
+        records: Some(records.clone()),
+        bots: 3,
+        ..server::Options::default()
+    };
+    let server = server::Server::bind("127.0.0.1:0").unwrap()
+        .with_options(options);
+    let addr = server.local_addr().unwrap().to_string();
+    thread::spawn(move || server.run());
+    client::bot(addr, Seat::Table("main".into()));
+    // The round was played to the end without its checkpoint.
+    assert_eq!(1, fs::read_dir(&records).unwrap().count());
+    fs::remove_dir_all(&records).unwrap();
 }